@@ -1,22 +1,77 @@
-use std::{env, path::PathBuf};
+use std::{env, ffi::OsString, path::PathBuf};
 
-use clap::{arg, Command, Error};
+use clap::{arg, ArgMatches, Command, Error, ErrorKind};
+use glob::Pattern;
+use regex::Regex;
 
-use crate::core::task::Task;
+use crate::core::{
+    combo::{ComboParser, ComboSplit, Schema},
+    importer::{FieldMapping, InputFormat},
+    output_format::OutputFormat,
+    results_layout::{CollisionPolicy, ResultsLayout},
+    set_op::{Emit, SetOp},
+    shard_mode::ShardMode,
+    slice::Slice,
+    sort_key::{SortKey, SortOptions},
+    split_key::SplitKey,
+    targets::{SymlinkPolicy, TargetFilter},
+    task::Task,
+    utils,
+};
 
 pub struct Args {
     pub task: Task,
     pub n: Option<usize>,
     pub targets: Vec<PathBuf>,
     pub compare_with: Option<PathBuf>,
+    pub op: SetOp,
+    pub index: Option<PathBuf>,
+    pub fp_rate: Option<f64>,
+    pub exact: bool,
+    pub emit: Option<Emit>,
+    pub attribution: bool,
+    pub sorted: bool,
+    pub sort: SortOptions,
+    pub unique: bool,
+    pub memory: usize,
+    pub max_bytes: Option<usize>,
+    pub split_key: SplitKey,
+    pub max_open: usize,
+    pub min_group: usize,
+    pub shard_by: ShardMode,
+    pub dedup: bool,
+    pub interleave: bool,
+    pub output: Option<String>,
+    pub layout: ResultsLayout,
+    pub seed: Option<u64>,
+    pub joint: bool,
+    pub percent: Option<f64>,
+    pub stratify: Option<SplitKey>,
+    pub slice: Option<Slice>,
+    pub combo: ComboParser,
+    pub input_format: InputFormat,
+    pub map: Vec<FieldMapping>,
+    pub table: Option<String>,
+    pub patterns: Vec<Regex>,
     pub binary_path: PathBuf,
 }
 
 pub fn parse_args() -> Result<Args, Error> {
+    parse_args_from(env::args_os())
+}
+
+/// Arguments of the command line, the first one is the path of the binary
+pub fn parse_args_from<I, T>(args: I) -> Result<Args, Error>
+where
+    I: IntoIterator<Item = T>,
+    T: Into<OsString>,
+{
+    let args: Vec<OsString> = args.into_iter().map(Into::into).collect();
+    let binary_path = PathBuf::from(args.first().cloned().unwrap_or_default());
     let matches = Command::new("combosutils-rs")
         .arg(arg!(--task <task>).possible_values(Task::possible_values()))
         .arg(
-            arg!(-n <n> "Number of lines/parts")
+            arg!(-n <n> "Number of lines/parts/shards/sampled lines, or outputs of a joint shuffle")
                 .required(false)
                 .takes_value(true)
                 .validator(|s| {
@@ -32,14 +87,51 @@ pub fn parse_args() -> Result<Args, Error> {
                 }),
         )
         .arg(
-            arg!(--target <target> "File(s) for processing")
+            arg!(--target <target> "File(s), directories or glob patterns for processing")
                 .required(true)
                 .takes_value(true)
                 .multiple_values(true),
         )
         .arg(
-            arg!(--with <file> "Compare with file/dir")
-                .required(true)
+            arg!(--include <pattern> "Take only matching files from directories")
+                .required(false)
+                .takes_value(true)
+                .multiple_values(true)
+                .validator(|s| Pattern::new(s).map(|_| ()).map_err(|e| e.to_string())),
+        )
+        .arg(
+            arg!(--exclude <pattern> "Skip matching files in directories")
+                .required(false)
+                .takes_value(true)
+                .multiple_values(true)
+                .validator(|s| Pattern::new(s).map(|_| ()).map_err(|e| e.to_string())),
+        )
+        .arg(
+            arg!(--depth <n> "Max depth of directories recursion")
+                .required(false)
+                .takes_value(true)
+                .validator(|s| match s.parse::<usize>() {
+                    Ok(n) if n > 0 => Ok(()),
+                    _ => Err(String::from("must be a number > 0")),
+                }),
+        )
+        .arg(
+            arg!(--symlinks <policy> "Symbolic links in directories")
+                .required(false)
+                .default_value("files")
+                .possible_values(SymlinkPolicy::possible_values()),
+        )
+        .arg(arg!(--mirror "Repeat directories structure of the targets in results"))
+        .arg(
+            arg!(--"on-collision" <policy> "What to do with results of targets with the same names")
+                .required(false)
+                .default_value("suffix")
+                .possible_values(CollisionPolicy::possible_values()),
+        )
+        .arg(
+            arg!(--with <file> "Compare with file/dir/hash index")
+                .required(false)
+                .required_if_eq("task", "compare")
                 .takes_value(true)
                 .validator(|s| {
                     let path: PathBuf = s.to_owned().into();
@@ -50,22 +142,419 @@ pub fn parse_args() -> Result<Args, Error> {
                     Ok(())
                 }),
         )
-        .get_matches();
+        .arg(
+            arg!(--op <op> "Set operation for compare")
+                .required(false)
+                .default_value("diff")
+                .possible_values(SetOp::possible_values()),
+        )
+        .arg(
+            arg!(--index <file> "Hash index to create or extend")
+                .required(false)
+                .required_if_eq("task", "build-index")
+                .takes_value(true),
+        )
+        .arg(
+            arg!(--"fp-rate" <rate> "Compare through a bloom filter with this false positive rate")
+                .required(false)
+                .takes_value(true)
+                .validator(|s| match s.parse::<f64>() {
+                    Ok(rate) if rate > 0.0 && rate < 1.0 => Ok(()),
+                    _ => Err(String::from("must be a number between 0 and 1")),
+                }),
+        )
+        .arg(arg!(--exact "Check bloom filter hits against compare files").requires("fp-rate"))
+        .arg(
+            arg!(--emit <emit> "Compare output: unique, matched or both")
+                .required(false)
+                .possible_values(Emit::possible_values()),
+        )
+        .arg(arg!(--attribution "Add source file of every matched line"))
+        .arg(arg!(--sorted "Compare sorted files in a single pass").conflicts_with("fp-rate"))
+        .arg(
+            arg!(--key <key> "Sort key")
+                .required(false)
+                .default_value("line")
+                .possible_values(SortKey::possible_values()),
+        )
+        .arg(arg!(--"ignore-case" "Compare sort keys case-insensitively"))
+        .arg(arg!(--numeric "Compare sort keys as numbers"))
+        .arg(arg!(--reverse "Sort in descending order"))
+        .arg(arg!(--unique "Keep only the first line of equal sort keys"))
+        .arg(
+            arg!(--memory <mb> "Memory budget in megabytes")
+                .required(false)
+                .default_value("1024")
+                .validator(|s| match s.parse::<usize>() {
+                    Ok(n) if n > 0 => Ok(()),
+                    _ => Err(String::from("must be a number > 0")),
+                }),
+        )
+        .arg(
+            arg!(--"max-bytes" <size> "Max size of every part, e.g. 100MB")
+                .required(false)
+                .validator(|s| match utils::parse_size(s) {
+                    Some(n) if n > 0 => Ok(()),
+                    _ => Err(String::from(
+                        "must be a size > 0 like 1048576, 512KB, 100MB or 2GB",
+                    )),
+                }),
+        )
+        .arg(
+            arg!(--"split-key" <key> "Key which picks the output file")
+                .required(false)
+                .default_value("domain")
+                .possible_values(SplitKey::possible_values()),
+        )
+        .arg(
+            arg!(--"max-open" <n> "Max number of open output files")
+                .required(false)
+                .default_value("256")
+                .validator(|s| match s.parse::<usize>() {
+                    Ok(n) if n > 0 => Ok(()),
+                    _ => Err(String::from("must be a number > 0")),
+                }),
+        )
+        .arg(
+            arg!(--"min-group" <n> "Groups with fewer lines go to the \"other\" file")
+                .required(false)
+                .default_value("1")
+                .validator(|s| match s.parse::<usize>() {
+                    Ok(n) if n > 0 => Ok(()),
+                    _ => Err(String::from("must be a number > 0")),
+                }),
+        )
+        .arg(
+            arg!(--"shard-by" <mode> "How lines are distributed between shards")
+                .required(false)
+                .default_value("round-robin")
+                .possible_values(ShardMode::possible_values()),
+        )
+        .arg(arg!(--dedup "Drop lines which were already merged"))
+        .arg(arg!(--interleave "Merge one line from every file in turn"))
+        .arg(
+            arg!(--output <name> "Name of the merged, jointly shuffled or sampled file")
+                .required(false)
+                .takes_value(true)
+                .validator(|s| {
+                    if s.is_empty() || s.contains(['/', '\\']) {
+                        return Err(String::from("must be a file name without directories"));
+                    }
+                    Ok(())
+                }),
+        )
+        .arg(
+            arg!(--seed <seed> "Seed of the shuffle, a random one is printed if it's not set. Replays need the same --memory too")
+                .required(false)
+                .takes_value(true)
+                .validator(|s| match s.parse::<u64>() {
+                    Ok(_) => Ok(()),
+                    Err(_) => Err(String::from("must be a number from 0 to 18446744073709551615")),
+                }),
+        )
+        .arg(arg!(--joint "Shuffle or sample all targets together"))
+        .arg(
+            arg!(--percent <percent> "Sample this share of lines instead of -n lines")
+                .required(false)
+                .takes_value(true)
+                .validator(|s| match s.parse::<f64>() {
+                    Ok(percent) if percent > 0.0 && percent <= 100.0 => Ok(()),
+                    _ => Err(String::from("must be a number from 0 to 100")),
+                }),
+        )
+        .arg(
+            arg!(--stratify <key> "Sample -n lines of every group with this key")
+                .required(false)
+                .possible_values(SplitKey::possible_values())
+                .conflicts_with("percent"),
+        )
+        .arg(
+            arg!(--head <n> "Slice the first lines")
+                .required(false)
+                .validator(|s| match s.parse::<usize>() {
+                    Ok(n) if n > 0 => Ok(()),
+                    _ => Err(String::from("must be a number > 0")),
+                })
+                .conflicts_with_all(&["tail", "lines", "bytes"]),
+        )
+        .arg(
+            arg!(--tail <n> "Slice the last lines")
+                .required(false)
+                .validator(|s| match s.parse::<usize>() {
+                    Ok(n) if n > 0 => Ok(()),
+                    _ => Err(String::from("must be a number > 0")),
+                })
+                .conflicts_with_all(&["lines", "bytes"]),
+        )
+        .arg(
+            arg!(--lines <range> "Slice lines from-to, numbered from 1, e.g. 1000000-2000000")
+                .required(false)
+                .validator(|s| match Slice::parse_lines(s) {
+                    Some(_) => Ok(()),
+                    None => Err(String::from("must be a range like 100-200 or 100-")),
+                })
+                .conflicts_with("bytes"),
+        )
+        .arg(
+            arg!(--bytes <range> "Slice lines which start in the bytes range, e.g. 50GB-51GB")
+                .required(false)
+                .validator(|s| match Slice::parse_bytes(s) {
+                    Some(_) => Ok(()),
+                    None => Err(String::from("must be a range like 1024-4096, 50GB-51GB or 2GB-")),
+                }),
+        )
+        .arg(
+            arg!(--separator <separator> "Separators of login and password, `tab` for the tab")
+                .required(false)
+                .multiple_values(true)
+                .default_values(&[":", ";"])
+                .validator(|s| match ComboParser::parse_separator(s) {
+                    Some(_) => Ok(()),
+                    None => Err(String::from("must not be empty")),
+                }),
+        )
+        .arg(
+            arg!(--"split-at" <split> "Which separator splits the combo")
+                .required(false)
+                .default_value("first")
+                .possible_values(ComboSplit::possible_values()),
+        )
+        .arg(arg!(--"keep-separator" "Write combos with their own separator instead of `:`"))
+        .arg(
+            arg!(--schema <fields> "Fields of the combos, e.g. email,password,* or username,email,password,ip")
+                .required(false)
+                .validator(|s| Schema::parse(s).map(|_| ())),
+        )
+        .arg(
+            arg!(--columns <fields> "Fields of the schema to write and their order, not used by extract-logins, extract-passwords and build-index")
+                .required(false)
+                .requires("schema"),
+        )
+        .arg(
+            arg!(--field <field> "Field of the schema used as the key of sort and split-by-key")
+                .required(false)
+                .requires("schema"),
+        )
+        .arg(
+            arg!(--"output-format" <format> "csv, jsonl or a template like {login};{password} or {password}\\t{login}, not used by extract-logins, extract-passwords and build-index")
+                .required(false)
+                .validator(|s| s.parse::<OutputFormat>().map(|_| ())),
+        )
+        .arg(
+            arg!(--"input-format" <format> "Format of the targets, CSV, JSON Lines, JSON arrays and SQL dumps are converted to combos")
+                .required(false)
+                .default_value("auto")
+                .possible_values(InputFormat::possible_values()),
+        )
+        .arg(
+            arg!(--map <mapping> "Columns or keys of the combo fields, e.g. login=email password=3")
+                .required(false)
+                .multiple_values(true)
+                .validator(|s| s.parse::<FieldMapping>().map(|_| ())),
+        )
+        .arg(
+            arg!(--table <name> "Table of SQL dumps which rows are imported, by default the first one with named login and password columns")
+                .required(false),
+        )
+        .arg(
+            arg!(--pattern <regex> "Patterns of extract-combos with login, password and optional separator groups")
+                .required(false)
+                .multiple_values(true)
+                .validator(validate_pattern),
+        )
+        .get_matches_from(args);
     let task: Task = matches.value_of_t("task")?;
     let n = matches.value_of("n").map(|s| s.parse::<usize>().unwrap());
-    let targets: Vec<PathBuf> = matches
-        .values_of("target")
-        .unwrap()
-        .map(PathBuf::from)
-        .collect();
+    let filter = TargetFilter {
+        include: patterns_of(&matches, "include"),
+        exclude: patterns_of(&matches, "exclude"),
+        depth: matches
+            .value_of("depth")
+            .map(|s| s.parse::<usize>().unwrap()),
+        symlinks: matches.value_of_t("symlinks")?,
+    };
+    let expanded = filter.expand(matches.values_of("target").unwrap());
+    if expanded.files.is_empty() {
+        return Err(Error::raw(
+            ErrorKind::InvalidValue,
+            "no files were found for --target\n",
+        ));
+    }
+    let targets = expanded.files;
+    let layout = ResultsLayout::new(
+        &targets,
+        expanded.rel_dirs,
+        matches.is_present("mirror"),
+        matches.value_of_t("on-collision")?,
+    )
+    .map_err(|err| Error::raw(ErrorKind::ArgumentConflict, format!("{}\n", err)))?;
     let compare_with = matches.value_of("with").map(PathBuf::from);
+    let op: SetOp = matches.value_of_t("op")?;
+    let index = matches.value_of("index").map(PathBuf::from);
+    let fp_rate = matches
+        .value_of("fp-rate")
+        .map(|s| s.parse::<f64>().unwrap());
+    let exact = matches.is_present("exact");
+    let emit: Option<Emit> = if matches.is_present("emit") {
+        Some(matches.value_of_t("emit")?)
+    } else {
+        None
+    };
+    let attribution = matches.is_present("attribution");
+    let sorted = matches.is_present("sorted");
+    let mut combo = ComboParser::new(
+        matches
+            .values_of("separator")
+            .unwrap()
+            .map(|s| ComboParser::parse_separator(s).unwrap())
+            .collect(),
+        matches.value_of_t("split-at")?,
+        matches.is_present("keep-separator"),
+    );
+    let field = matches.value_of("field").map(str::to_lowercase);
+    if let Some(schema) = matches.value_of("schema") {
+        let mut schema = Schema::parse(schema).unwrap();
+        if let Some(columns) = matches.value_of("columns") {
+            if !task.formats_output() {
+                return Err(Error::raw(
+                    ErrorKind::ArgumentConflict,
+                    "--columns doesn't work with extract-logins, extract-passwords and build-index\n",
+                ));
+            }
+            schema = schema
+                .with_columns(columns)
+                .map_err(|err| Error::raw(ErrorKind::InvalidValue, format!("{}\n", err)))?;
+        }
+        if let Some(field) = &field {
+            if !schema.has_field(field) {
+                return Err(Error::raw(
+                    ErrorKind::InvalidValue,
+                    format!("field {} is not in the schema\n", field),
+                ));
+            }
+        }
+        combo = combo.with_schema(schema);
+    }
+    if let Some(format) = matches.value_of("output-format") {
+        if !task.formats_output() {
+            return Err(Error::raw(
+                ErrorKind::ArgumentConflict,
+                "--output-format doesn't work with extract-logins, extract-passwords and build-index\n",
+            ));
+        }
+        combo = combo
+            .with_format(format.parse::<OutputFormat>().unwrap())
+            .map_err(|err| Error::raw(ErrorKind::InvalidValue, format!("{}\n", err)))?;
+    }
+    let sort = SortOptions {
+        key: matches.value_of_t("key")?,
+        ignore_case: matches.is_present("ignore-case"),
+        numeric: matches.is_present("numeric"),
+        reverse: matches.is_present("reverse"),
+        parser: combo.clone(),
+        field,
+    };
+    let unique = matches.is_present("unique");
+    let memory: usize = matches.value_of_t("memory")?;
+    let max_bytes = matches
+        .value_of("max-bytes")
+        .map(|s| utils::parse_size(s).unwrap());
+    let split_key: SplitKey = matches.value_of_t("split-key")?;
+    let max_open: usize = matches.value_of_t("max-open")?;
+    let min_group: usize = matches.value_of_t("min-group")?;
+    let shard_by: ShardMode = matches.value_of_t("shard-by")?;
+    let dedup = matches.is_present("dedup");
+    let interleave = matches.is_present("interleave");
+    let output = matches.value_of("output").map(str::to_owned);
+    let seed = matches.value_of("seed").map(|s| s.parse::<u64>().unwrap());
+    let joint = matches.is_present("joint");
+    let percent = matches
+        .value_of("percent")
+        .map(|s| s.parse::<f64>().unwrap());
+    let stratify: Option<SplitKey> = if matches.is_present("stratify") {
+        Some(matches.value_of_t("stratify")?)
+    } else {
+        None
+    };
+
+    let slice = if let Some(n) = matches.value_of("head") {
+        Some(Slice::Head(n.parse::<usize>().unwrap()))
+    } else if let Some(n) = matches.value_of("tail") {
+        Some(Slice::Tail(n.parse::<usize>().unwrap()))
+    } else if let Some(range) = matches.value_of("lines") {
+        Slice::parse_lines(range)
+    } else {
+        matches.value_of("bytes").and_then(Slice::parse_bytes)
+    };
+    if matches!(task, Task::Slice) && slice.is_none() {
+        return Err(Error::raw(
+            ErrorKind::MissingRequiredArgument,
+            "slice needs one of --head, --tail, --lines or --bytes\n",
+        ));
+    }
+
+    let input_format: InputFormat = matches.value_of_t("input-format")?;
+    let map: Vec<FieldMapping> = matches
+        .values_of("map")
+        .map(|values| values.map(|s| s.parse().unwrap()).collect())
+        .unwrap_or_default();
+    let table = matches.value_of("table").map(str::to_owned);
+    let patterns: Vec<Regex> = matches
+        .values_of("pattern")
+        .map(|values| values.map(|s| Regex::new(s).unwrap()).collect())
+        .unwrap_or_default();
 
-    let binary_path: PathBuf = env::args().next().unwrap().into();
     Ok(Args {
         task,
         n,
         targets,
         compare_with,
+        op,
+        index,
+        fp_rate,
+        exact,
+        emit,
+        attribution,
+        sorted,
+        sort,
+        unique,
+        memory,
+        max_bytes,
+        split_key,
+        max_open,
+        min_group,
+        shard_by,
+        dedup,
+        interleave,
+        output,
+        layout,
+        seed,
+        joint,
+        percent,
+        stratify,
+        slice,
+        combo,
+        input_format,
+        map,
+        table,
+        patterns,
         binary_path,
     })
 }
+
+fn patterns_of(matches: &ArgMatches, name: &str) -> Vec<Pattern> {
+    matches
+        .values_of(name)
+        .map(|values| values.map(|s| Pattern::new(s).unwrap()).collect())
+        .unwrap_or_default()
+}
+
+fn validate_pattern(pattern: &str) -> Result<(), String> {
+    let regex = Regex::new(pattern).map_err(|e| e.to_string())?;
+    for group in ["login", "password"] {
+        if !regex.capture_names().flatten().any(|name| name == group) {
+            return Err(format!("must have the (?P<{}>...) group", group));
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,63 @@
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+
+/// Bloom filter over line hashes. Bits are atomic so files can be inserted in parallel
+pub struct BloomFilter {
+    bits: Vec<AtomicU64>,
+    bits_count: u64,
+    hashes_count: u32,
+    inserted: AtomicUsize,
+}
+
+impl BloomFilter {
+    /// Sizes the filter for `expected` items with the false positive rate `fp_rate`
+    pub fn with_rate(expected: usize, fp_rate: f64) -> Self {
+        let expected = expected.max(1) as f64;
+        let ln2 = std::f64::consts::LN_2;
+
+        let bits_count = (-expected * fp_rate.ln() / (ln2 * ln2)).ceil().max(64.0) as u64;
+        let hashes_count = ((bits_count as f64 / expected) * ln2)
+            .round()
+            .clamp(1.0, 30.0) as u32;
+
+        let words = (bits_count / 64 + 1) as usize;
+        BloomFilter {
+            bits: (0..words).map(|_| AtomicU64::new(0)).collect(),
+            bits_count,
+            hashes_count,
+            inserted: AtomicUsize::new(0),
+        }
+    }
+
+    pub fn insert(&self, hash: u64) {
+        for bit in self.bit_indexes(hash) {
+            self.bits[(bit / 64) as usize].fetch_or(1 << (bit % 64), Ordering::Relaxed);
+        }
+        self.inserted.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn contains(&self, hash: u64) -> bool {
+        self.bit_indexes(hash).all(|bit| {
+            self.bits[(bit / 64) as usize].load(Ordering::Relaxed) & (1 << (bit % 64)) != 0
+        })
+    }
+
+    /// Size of the filter in bytes
+    pub fn size(&self) -> usize {
+        self.bits.len() * std::mem::size_of::<u64>()
+    }
+
+    /// False positive rate for the amount of actually inserted items
+    pub fn expected_fp_rate(&self) -> f64 {
+        let inserted = self.inserted.load(Ordering::Relaxed) as f64;
+        let k = self.hashes_count as f64;
+        (1.0 - (-k * inserted / self.bits_count as f64).exp()).powf(k)
+    }
+
+    // Kirsch-Mitzenmacher double hashing, the second hash is derived from the first one
+    fn bit_indexes(&self, hash: u64) -> impl Iterator<Item = u64> {
+        let h1 = hash;
+        let h2 = hash.rotate_left(32).wrapping_mul(0x9E37_79B9_7F4A_7C15) | 1;
+        let bits_count = self.bits_count;
+        (0..self.hashes_count as u64).map(move |i| h1.wrapping_add(i.wrapping_mul(h2)) % bits_count)
+    }
+}
@@ -0,0 +1,416 @@
+use std::{borrow::Cow, fmt, ops::Range, str::FromStr};
+
+use clap::{ArgEnum, PossibleValue};
+
+use super::output_format::{OutputFormat, EXTRA_FIELD};
+
+/// Which separator splits the combo when there are several of them in the line
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ArgEnum)]
+pub enum ComboSplit {
+    /// Passwords may contain separators
+    #[default]
+    First,
+    /// Logins may contain separators
+    Last,
+    /// Every separator starts a new field, fields after the password are extra
+    All,
+}
+
+impl ComboSplit {
+    pub fn possible_values() -> impl Iterator<Item = PossibleValue<'static>> {
+        ComboSplit::value_variants()
+            .iter()
+            .filter_map(ArgEnum::to_possible_value)
+    }
+}
+
+impl fmt::Display for ComboSplit {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ComboSplit::First => write!(f, "по первому разделителю"),
+            ComboSplit::Last => write!(f, "по последнему разделителю"),
+            ComboSplit::All => write!(f, "по всем разделителям"),
+        }
+    }
+}
+
+impl FromStr for ComboSplit {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let split = match s {
+            "first" => ComboSplit::First,
+            "last" => ComboSplit::Last,
+            "all" => ComboSplit::All,
+            _ => return Err("Такого варианта нет".to_owned()),
+        };
+
+        Ok(split)
+    }
+}
+
+/// Names of the login field in the schema, the first one found is the login
+const LOGIN_FIELDS: [&str; 4] = ["login", "email", "username", "phone"];
+const PASSWORD_FIELD: &str = "password";
+/// Schema field which takes any number of unnamed fields
+const ANY_FIELDS: &str = "*";
+
+/// Names of the fields of combo lines like `username,email,password,ip` or `email,password,*`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Schema {
+    fields: Vec<String>,
+    /// Fields written to the output and their order, all fields of the schema by default
+    columns: Option<Vec<String>>,
+}
+
+impl Schema {
+    pub fn parse(schema: &str) -> Result<Schema, String> {
+        let fields = parse_names(schema)?;
+
+        for (i, field) in fields.iter().enumerate() {
+            if fields[..i].contains(field) {
+                return Err(format!("field {} is repeated", field));
+            }
+        }
+        if !fields.iter().any(|field| field == PASSWORD_FIELD) {
+            return Err(String::from("schema must have the password field"));
+        }
+        if !LOGIN_FIELDS
+            .iter()
+            .any(|login| fields.iter().any(|field| field == login))
+        {
+            return Err(format!(
+                "schema must have one of the login fields: {}",
+                LOGIN_FIELDS.join(", ")
+            ));
+        }
+
+        Ok(Schema {
+            fields,
+            columns: None,
+        })
+    }
+
+    /// Projects or reorders fields on output, every column must be in the schema
+    pub fn with_columns(mut self, columns: &str) -> Result<Schema, String> {
+        let columns = parse_names(columns)?;
+        if let Some(column) = columns.iter().find(|column| !self.fields.contains(column)) {
+            return Err(format!("column {} is not in the schema", column));
+        }
+
+        self.columns = Some(columns);
+        Ok(self)
+    }
+
+    /// Fields taken by `*` can't be addressed by name
+    pub fn has_field(&self, name: &str) -> bool {
+        name != ANY_FIELDS && self.fields.iter().any(|field| field == name)
+    }
+
+    /// Fields of the line taken by every field of the schema, `None` when their numbers don't match
+    fn ranges(&self, fields_n: usize) -> Option<Vec<Range<usize>>> {
+        let any = self.fields.iter().position(|field| field == ANY_FIELDS);
+        let any_n = match any {
+            Some(_) => fields_n.checked_sub(self.fields.len() - 1)?,
+            None if fields_n == self.fields.len() => 0,
+            None => return None,
+        };
+
+        let ranges = (0..self.fields.len())
+            .map(|i| match any {
+                Some(any) if i == any => i..i + any_n,
+                Some(any) if i > any => i - 1 + any_n..i + any_n,
+                _ => i..i + 1,
+            })
+            .collect();
+
+        Some(ranges)
+    }
+
+    fn position(&self, name: &str) -> Option<usize> {
+        self.fields.iter().position(|field| field == name)
+    }
+
+    fn login_position(&self) -> usize {
+        LOGIN_FIELDS
+            .iter()
+            .find_map(|login| self.position(login))
+            .unwrap()
+    }
+}
+
+fn parse_names(names: &str) -> Result<Vec<String>, String> {
+    let names: Vec<String> = names
+        .split(',')
+        .map(|name| name.trim().to_lowercase())
+        .collect();
+    if names.iter().any(String::is_empty) {
+        return Err(String::from("field names must not be empty"));
+    }
+    if names.iter().filter(|name| *name == ANY_FIELDS).count() > 1 {
+        return Err(format!("{} may be used only once", ANY_FIELDS));
+    }
+    Ok(names)
+}
+
+/// Combo line split into its fields
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Combo<'a> {
+    pub login: &'a str,
+    pub password: &'a str,
+    pub extra: Vec<&'a str>,
+    pub separator: &'a str,
+    /// All fields of the line when it's parsed by a schema
+    pub fields: Vec<&'a str>,
+}
+
+impl<'a> Combo<'a> {
+    /// Combo with non-empty login and password
+    pub fn is_valid(&self) -> bool {
+        !self.login.is_empty() && !self.password.is_empty()
+    }
+}
+
+/// Splits combo lines into fields and joins them back
+#[derive(Debug, Clone)]
+pub struct ComboParser {
+    separators: Vec<String>,
+    split: ComboSplit,
+    keep_separator: bool,
+    schema: Option<Schema>,
+    format: OutputFormat,
+}
+
+impl Default for ComboParser {
+    fn default() -> Self {
+        ComboParser {
+            separators: vec![":".to_owned(), ";".to_owned()],
+            split: ComboSplit::First,
+            keep_separator: false,
+            schema: None,
+            format: OutputFormat::Combo,
+        }
+    }
+}
+
+impl ComboParser {
+    /// Without `keep_separator` combos are written with `:`
+    pub fn new(separators: Vec<String>, split: ComboSplit, keep_separator: bool) -> Self {
+        ComboParser {
+            separators,
+            split,
+            keep_separator,
+            schema: None,
+            format: OutputFormat::Combo,
+        }
+    }
+
+    /// With the schema every separator starts a new field, so the split mode is not used
+    pub fn with_schema(mut self, schema: Schema) -> Self {
+        self.schema = Some(schema);
+        self
+    }
+
+    /// Fields of the template must be `login`, `password`, `extra` or fields of the schema
+    pub fn with_format(mut self, format: OutputFormat) -> Result<Self, String> {
+        for name in format.fields() {
+            let known = match &self.schema {
+                Some(schema) => schema.has_field(name),
+                None => false,
+            };
+            if !known && !matches!(name, "login" | "password" | EXTRA_FIELD) {
+                return Err(format!("field {} of the output format is unknown", name));
+            }
+        }
+
+        self.format = format;
+        Ok(self)
+    }
+
+    /// Separator names accepted on the command line, `tab` and `\t` mean the tab character
+    pub fn parse_separator(separator: &str) -> Option<String> {
+        match separator {
+            "" => None,
+            "tab" | "\\t" => Some("\t".to_owned()),
+            separator => Some(separator.to_owned()),
+        }
+    }
+
+    pub fn separators(&self) -> &[String] {
+        &self.separators
+    }
+
+    /// Combo with non-empty login and password, `None` for any other line
+    pub fn parse<'a>(&self, line: &'a str) -> Option<Combo<'a>> {
+        self.split(line).filter(Combo::is_valid)
+    }
+
+    /// Fields of the line, they may be empty
+    pub fn split<'a>(&self, line: &'a str) -> Option<Combo<'a>> {
+        if let Some(schema) = &self.schema {
+            return split_by_schema(schema, line, self.find_separator(line, false)?.1);
+        }
+
+        let (pos, separator) = match self.split {
+            ComboSplit::First | ComboSplit::All => self.find_separator(line, false)?,
+            ComboSplit::Last => self.find_separator(line, true)?,
+        };
+        let login = &line[..pos];
+        let rest = &line[pos + separator.len()..];
+
+        let (password, extra) = match self.split {
+            ComboSplit::All => {
+                let mut fields = rest.split(separator);
+                let password = fields.next().unwrap_or_default();
+                (password, fields.collect())
+            }
+            _ => (rest, Vec::new()),
+        };
+
+        Some(Combo {
+            login,
+            password,
+            extra,
+            separator,
+            fields: Vec::new(),
+        })
+    }
+
+    /// Field of the combo by its name in the schema, fields taken by `*` can't be addressed
+    pub fn field<'a>(&self, combo: &Combo<'a>, name: &str) -> Option<&'a str> {
+        let schema = self.schema.as_ref()?;
+        let ranges = schema.ranges(combo.fields.len())?;
+        let range = ranges.get(schema.position(name)?)?;
+        (range.len() == 1).then(|| combo.fields[range.start])
+    }
+
+    /// Whether lines are rewritten on output by the output format or the columns of the schema
+    pub fn formats_output(&self) -> bool {
+        self.format != OutputFormat::Combo
+            || matches!(&self.schema, Some(schema) if schema.columns.is_some())
+    }
+
+    /// Combo line in the output format, lines which aren't combos are kept as they are
+    pub fn format_line<'a>(&self, line: &'a str) -> Cow<'a, str> {
+        if !self.formats_output() {
+            return Cow::Borrowed(line);
+        }
+        match self.parse(line) {
+            Some(combo) => Cow::Owned(self.join(&combo, combo.login, combo.password)),
+            None => Cow::Borrowed(line),
+        }
+    }
+
+    /// `format_line` of the owned line, it's returned as it is when the output isn't formatted
+    pub fn format_string(&self, line: String) -> String {
+        if !self.formats_output() {
+            return line;
+        }
+        self.format_line(&line).into_owned()
+    }
+
+    /// Line in the output format with the login and password replaced, extra fields are kept
+    pub fn join(&self, combo: &Combo, login: &str, password: &str) -> String {
+        let separator = if self.keep_separator {
+            combo.separator
+        } else {
+            ":"
+        };
+
+        let record = match &self.schema {
+            Some(schema) => record_by_schema(schema, combo, login, password),
+            None => {
+                let mut record = vec![("login", login), (PASSWORD_FIELD, password)];
+                record.extend(combo.extra.iter().map(|field| (EXTRA_FIELD, *field)));
+                record
+            }
+        };
+
+        self.format.write(&record, login, password, separator)
+    }
+
+    /// Position of the first or the last separator in the line,
+    /// the longest one wins when several separators start there
+    fn find_separator<'a>(&self, line: &'a str, last: bool) -> Option<(usize, &'a str)> {
+        let mut found: Option<(usize, &'a str)> = None;
+
+        for separator in &self.separators {
+            let pos = if last {
+                line.rfind(separator.as_str())
+            } else {
+                line.find(separator.as_str())
+            };
+            let pos = match pos {
+                Some(pos) => pos,
+                None => continue,
+            };
+
+            let better = match found {
+                None => true,
+                Some((found_pos, found_separator)) if pos == found_pos => {
+                    separator.len() > found_separator.len()
+                }
+                Some((found_pos, _)) => (pos > found_pos) == last,
+            };
+            if better {
+                found = Some((pos, &line[pos..pos + separator.len()]));
+            }
+        }
+
+        found
+    }
+}
+
+fn split_by_schema<'a>(schema: &Schema, line: &'a str, separator: &'a str) -> Option<Combo<'a>> {
+    let fields: Vec<&str> = line.split(separator).collect();
+    let ranges = schema.ranges(fields.len())?;
+
+    let login = ranges[schema.login_position()].start;
+    let password = ranges[schema.position(PASSWORD_FIELD).unwrap()].start;
+    let extra = fields
+        .iter()
+        .enumerate()
+        .filter(|(i, _)| *i != login && *i != password)
+        .map(|(_, field)| *field)
+        .collect();
+
+    Some(Combo {
+        login: fields[login],
+        password: fields[password],
+        extra,
+        separator,
+        fields,
+    })
+}
+
+/// Columns of the schema with their names, fields taken by `*` are extra
+fn record_by_schema<'a>(
+    schema: &'a Schema,
+    combo: &Combo<'a>,
+    login: &'a str,
+    password: &'a str,
+) -> Vec<(&'a str, &'a str)> {
+    let ranges = match schema.ranges(combo.fields.len()) {
+        Some(ranges) => ranges,
+        None => return vec![("login", login), (PASSWORD_FIELD, password)],
+    };
+    let login_position = schema.login_position();
+
+    let columns = schema.columns.as_ref().unwrap_or(&schema.fields);
+    let mut record: Vec<(&str, &str)> = Vec::with_capacity(combo.fields.len());
+    for column in columns {
+        let position = schema.position(column).unwrap();
+        if position == login_position {
+            record.push((column, login));
+        } else if column == PASSWORD_FIELD {
+            record.push((column, password));
+        } else if column == ANY_FIELDS {
+            let fields = &combo.fields[ranges[position].clone()];
+            record.extend(fields.iter().map(|field| (EXTRA_FIELD, *field)));
+        } else {
+            record.push((column, combo.fields[ranges[position].start]));
+        }
+    }
+
+    record
+}
@@ -1,18 +1,24 @@
 use std::{
+    fs,
     path::{Path, PathBuf},
     process::Command,
 };
 
 use chrono::{DateTime, Local};
 
-use super::{lines_processor::LinesProcessor, task::Task};
+use super::{
+    hash_index::HashIndex, importer::Importer, lines_processor::LinesProcessor, task::Task, utils,
+};
 use crate::{
     cmd::Args,
     errors::core_error::CoreError,
     processors::{
-        compare::Comparer, duplicates::*, extract_logins_passwords::PartExtractor,
+        build_index::IndexBuilder, compare::Comparer, duplicates::*,
+        extract_combos::ComboExtractor, extract_logins_passwords::PartExtractor,
         extract_phones::PhonesExtractor, merge::Merger, remove_domain::DomainRemover,
-        shuffle::Shuffler, split_by_lines::ByLinesSplitter, split_by_parts::ByPartsSplitter,
+        sample::Sampler, shard::Sharder, shuffle::Shuffler, slice::Slicer, sort::ExternalSorter,
+        split_by_key::ByKeySplitter, split_by_lines::ByLinesSplitter,
+        split_by_parts::ByPartsSplitter, split_by_size::BySizeSplitter,
     },
 };
 
@@ -39,9 +45,19 @@ impl Core {
         })
     }
 
-    pub fn process(self) -> Result<(), CoreError> {
+    pub fn process(mut self) -> Result<(), CoreError> {
+        let import_path = self.results_path.join(".import");
+        let imported = match self.import_targets(&import_path) {
+            Ok(imported) => imported,
+            Err(err) => {
+                Core::remove_imported(&import_path, &self.results_path);
+                return Err(err);
+            }
+        };
+
         let results_path = self.results_path.clone();
-        match self.args.task {
+        let task = self.args.task;
+        let processed = match self.args.task {
             Task::RemoveDomains => {
                 DomainRemover::new(self.args, results_path, self.save_period).process()
             }
@@ -62,9 +78,21 @@ impl Core {
                 ByPartsSplitter::new(self.args, results_path, self.save_period).process()
             }
 
+            Task::SplitBySize => {
+                BySizeSplitter::new(self.args, results_path, self.save_period).process()
+            }
+
+            Task::SplitByKey => {
+                ByKeySplitter::new(self.args, results_path, self.save_period).process()
+            }
+
+            Task::Shard => Sharder::new(self.args, results_path, self.save_period).process(),
+
             Task::Merge => Merger::new(self.args, results_path, self.save_period).process(),
 
             Task::Shuffle => Shuffler::new(self.args, results_path, self.save_period).process(),
+            Task::Sample => Sampler::new(self.args, results_path, self.save_period).process(),
+            Task::Slice => Slicer::new(self.args, results_path, self.save_period).process(),
 
             Task::ExtractLogins | Task::ExtractPasswords => {
                 PartExtractor::new(self.args, results_path, self.save_period).process()
@@ -74,12 +102,33 @@ impl Core {
                 PhonesExtractor::new(self.args, results_path, self.save_period).process()
             }
 
+            Task::ExtractCombos => {
+                ComboExtractor::new(self.args, results_path, self.save_period).process()
+            }
+
             Task::ExtractDuplicates => {
                 DuplicatesExtractor::new(self.args, results_path, self.save_period).process()
             }
 
             Task::Compare => Comparer::new(self.args, results_path, self.save_period).process(),
-        }?;
+
+            Task::Sort => ExternalSorter::new(self.args, results_path, self.save_period).process(),
+
+            Task::BuildIndex => {
+                IndexBuilder::new(self.args, results_path, self.save_period).process()
+            }
+        };
+
+        if imported {
+            Core::remove_imported(&import_path, &self.results_path);
+        }
+
+        processed?;
+
+        // the index is written to `--index`, so there is no results directory
+        if let Task::BuildIndex = task {
+            return Ok(());
+        }
 
         if !self.results_path.exists() {
             return Err(CoreError::NoResults);
@@ -90,6 +139,100 @@ impl Core {
         Ok(())
     }
 
+    /// Replaces CSV, JSON and SQL targets with their combos, returns whether there were such targets
+    fn import_targets(&mut self, import_path: &Path) -> Result<bool, CoreError> {
+        let importer = Importer::new(
+            self.args.input_format,
+            self.args.combo.separators().to_vec(),
+            self.args.map.clone(),
+            self.args.table.clone(),
+            self.save_period,
+        );
+
+        let mut imported = false;
+        let mut targets = Vec::with_capacity(self.args.targets.len());
+        let mut last_error = None;
+        for (target_num, target) in self.args.targets.drain(..).enumerate() {
+            // targets with the same names are imported into different directories
+            let target_import_path = import_path.join(target_num.to_string());
+            match importer.import(&target, &target_import_path) {
+                Ok(Some(imported_path)) => {
+                    self.args.layout.replace_target(&target, &imported_path);
+                    targets.push(imported_path);
+                    imported = true;
+                }
+                Ok(None) => targets.push(target),
+                Err(err) => {
+                    eprintln!("Can't import file {}. {}", target.display(), err);
+                    if target_import_path.exists() {
+                        if let Err(err) = fs::remove_dir_all(&target_import_path) {
+                            eprintln!(
+                                "Can't remove directory {}. {}",
+                                target_import_path.display(),
+                                err
+                            );
+                        }
+                    }
+                    last_error = Some(err);
+                }
+            }
+        }
+        // processors expect at least one target
+        if targets.is_empty() {
+            return Err(last_error.unwrap_or(CoreError::NoResults));
+        }
+        self.args.targets = targets;
+
+        if let Some(compare_with) = &self.args.compare_with {
+            let with_import_path = import_path.join("with");
+            if let Some(imported_path) =
+                Core::import_compare_with(&importer, compare_with, &with_import_path)?
+            {
+                self.args.compare_with = Some(imported_path);
+                imported = true;
+            }
+        }
+
+        Ok(imported)
+    }
+
+    /// `--with` file is imported like the targets, files of `--with` directories aren't imported
+    fn import_compare_with(
+        importer: &Importer,
+        compare_with: &Path,
+        import_path: &Path,
+    ) -> Result<Option<PathBuf>, CoreError> {
+        if HashIndex::is_index(compare_with) {
+            return Ok(None);
+        }
+        if compare_with.is_file() {
+            return importer.import(compare_with, import_path);
+        }
+
+        for path in utils::list_dir(compare_with.to_owned())? {
+            if importer.needs_import(&path) {
+                return Err(CoreError::Import {
+                    path,
+                    reason: String::from(
+                        "files of --with directories aren't imported, pass the file with --with",
+                    ),
+                });
+            }
+        }
+
+        Ok(None)
+    }
+
+    fn remove_imported(import_path: &Path, results_path: &Path) {
+        if import_path.exists() {
+            if let Err(err) = fs::remove_dir_all(import_path) {
+                eprintln!("Can't remove directory {}. {}", import_path.display(), err);
+            }
+        }
+        // the results directory was created for the imported files only when it's empty now
+        let _ = fs::remove_dir(results_path);
+    }
+
     fn open_in_explorer(path: PathBuf) {
         Command::new("explorer").arg(path).spawn().unwrap();
     }
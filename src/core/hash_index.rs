@@ -0,0 +1,133 @@
+use std::{
+    fs::{self, File},
+    io::{self, BufWriter, Write},
+    path::{Path, PathBuf},
+};
+
+use memmap::{Mmap, MmapOptions};
+
+use super::utils::open_file_r;
+
+const MAGIC: &[u8; 8] = b"CUHIDX01";
+const HASH_SIZE: usize = std::mem::size_of::<u64>();
+
+/// On-disk index of line hashes: `MAGIC` followed by sorted unique little-endian `u64`s.
+/// The file is memory-mapped, lookups are binary searches so loading is instant
+pub struct HashIndex {
+    mmap: Mmap,
+    len: usize,
+}
+
+impl HashIndex {
+    pub const EXTENSION: &'static str = "cuidx";
+
+    pub fn is_index(path: &Path) -> bool {
+        path.is_file() && path.extension().unwrap_or_default() == HashIndex::EXTENSION
+    }
+
+    pub fn open(path: &Path) -> io::Result<Self> {
+        let file = open_file_r(path)?;
+        let mmap = unsafe { MmapOptions::new().map(&file)? };
+
+        let len = mmap.len().saturating_sub(MAGIC.len()) / HASH_SIZE;
+
+        if !mmap.starts_with(MAGIC) || MAGIC.len() + len * HASH_SIZE != mmap.len() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("{} is not a hash index", path.display()),
+            ));
+        }
+
+        Ok(HashIndex { mmap, len })
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn contains(&self, hash: &u64) -> bool {
+        let (mut low, mut high) = (0usize, self.len);
+        while low < high {
+            let mid = low + (high - low) / 2;
+            match self.get(mid).cmp(hash) {
+                std::cmp::Ordering::Less => low = mid + 1,
+                std::cmp::Ordering::Greater => high = mid,
+                std::cmp::Ordering::Equal => return true,
+            }
+        }
+        false
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = u64> + '_ {
+        (0..self.len).map(|i| self.get(i))
+    }
+
+    fn get(&self, i: usize) -> u64 {
+        let start = MAGIC.len() + i * HASH_SIZE;
+        u64::from_le_bytes(self.mmap[start..start + HASH_SIZE].try_into().unwrap())
+    }
+
+    /// Merges sorted unique `hashes` into the index at `path`, creating it if needed.
+    /// Returns the number of hashes in the resulting index
+    pub fn extend(path: &Path, hashes: impl Iterator<Item = io::Result<u64>>) -> io::Result<usize> {
+        let existing = if path.exists() {
+            Some(HashIndex::open(path)?)
+        } else {
+            None
+        };
+
+        let mut tmp_path = PathBuf::from(path);
+        tmp_path.set_extension("tmp");
+
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        let mut writer = BufWriter::new(File::create(&tmp_path)?);
+        writer.write_all(MAGIC)?;
+
+        let mut written = 0usize;
+        let mut old = existing.iter().flat_map(|index| index.iter()).peekable();
+        let mut new = hashes.peekable();
+
+        loop {
+            let new_hash = match new.peek() {
+                Some(Ok(hash)) => Some(*hash),
+                Some(Err(_)) => return Err(new.next().unwrap().unwrap_err()),
+                None => None,
+            };
+
+            let next = match (old.peek(), new_hash) {
+                (Some(&a), Some(b)) if a < b => old.next(),
+                (Some(&a), Some(b)) if a > b => {
+                    new.next();
+                    Some(b)
+                }
+                (Some(_), Some(_)) => {
+                    new.next();
+                    old.next()
+                }
+                (Some(_), None) => old.next(),
+                (None, Some(b)) => {
+                    new.next();
+                    Some(b)
+                }
+                (None, None) => break,
+            };
+
+            if let Some(hash) = next {
+                writer.write_all(&hash.to_le_bytes())?;
+                written += 1;
+            }
+        }
+
+        writer.flush()?;
+        drop(writer);
+        drop(old);
+        // mapping must be released before the file can be replaced on Windows
+        drop(existing);
+
+        fs::rename(&tmp_path, path)?;
+        Ok(written)
+    }
+}
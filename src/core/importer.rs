@@ -0,0 +1,809 @@
+use std::{
+    collections::HashMap,
+    fmt,
+    fs::File,
+    io::{self, BufRead, Read},
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+use clap::{ArgEnum, PossibleValue};
+use serde::{
+    de::{self, MapAccess, SeqAccess, Visitor},
+    Deserialize, Deserializer,
+};
+use serde_json::Value;
+
+use super::{
+    sql_dump::{SqlItem, SqlScanner},
+    utils::{self, open_file_r},
+};
+use crate::errors::core_error::CoreError;
+
+/// Column names which are taken as the login and the password when they are not mapped
+const LOGIN_COLUMNS: [&str; 7] = [
+    "login", "email", "e-mail", "mail", "username", "user", "phone",
+];
+const PASSWORD_COLUMNS: [&str; 4] = ["password", "pass", "passwd", "pwd"];
+const CSV_DELIMITERS: [u8; 4] = *b",;\t|";
+
+/// Format of the targets, structured ones are converted to combo lines before the task
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ArgEnum)]
+pub enum InputFormat {
+    /// By the extension of the file
+    #[default]
+    Auto,
+    Text,
+    Csv,
+    /// JSON Lines or a JSON array of objects
+    Jsonl,
+    /// INSERT statements of SQL dumps
+    Sql,
+}
+
+impl InputFormat {
+    pub fn possible_values() -> impl Iterator<Item = PossibleValue<'static>> {
+        InputFormat::value_variants()
+            .iter()
+            .filter_map(ArgEnum::to_possible_value)
+    }
+
+    fn detect(self, path: &Path) -> InputFormat {
+        if self != InputFormat::Auto {
+            return self;
+        }
+
+        let extension = path
+            .extension()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .to_lowercase();
+        match extension.as_str() {
+            "csv" | "tsv" => InputFormat::Csv,
+            "jsonl" | "ndjson" | "json" => InputFormat::Jsonl,
+            "sql" => InputFormat::Sql,
+            _ => InputFormat::Text,
+        }
+    }
+}
+
+impl fmt::Display for InputFormat {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            InputFormat::Auto => write!(f, "по расширению"),
+            InputFormat::Text => write!(f, "текст"),
+            InputFormat::Csv => write!(f, "CSV"),
+            InputFormat::Jsonl => write!(f, "JSON"),
+            InputFormat::Sql => write!(f, "SQL"),
+        }
+    }
+}
+
+impl FromStr for InputFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let format = match s {
+            "auto" => InputFormat::Auto,
+            "text" => InputFormat::Text,
+            "csv" => InputFormat::Csv,
+            "jsonl" => InputFormat::Jsonl,
+            "sql" => InputFormat::Sql,
+            _ => return Err("Такого формата нет".to_owned()),
+        };
+
+        Ok(format)
+    }
+}
+
+/// Combo field and the column or key it's taken from, like `login=email` or `password=3`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FieldMapping {
+    pub field: String,
+    pub column: String,
+}
+
+impl FromStr for FieldMapping {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.split_once('=') {
+            Some((field, column)) if !field.trim().is_empty() && !column.trim().is_empty() => {
+                Ok(FieldMapping {
+                    field: field.trim().to_lowercase(),
+                    column: column.trim().to_owned(),
+                })
+            }
+            _ => Err(String::from("must be like login=email or password=3")),
+        }
+    }
+}
+
+/// Converts CSV, JSON and SQL targets into `login:password[:extra]` lines
+pub struct Importer {
+    format: InputFormat,
+    /// Separators of the combos, fields are joined with the first one
+    separators: Vec<String>,
+    mapping: Vec<FieldMapping>,
+    /// Table of SQL dumps, by default the first one with named login and password columns
+    table: Option<String>,
+    save_period: usize,
+}
+
+impl Importer {
+    pub fn new(
+        format: InputFormat,
+        separators: Vec<String>,
+        mapping: Vec<FieldMapping>,
+        table: Option<String>,
+        save_period: usize,
+    ) -> Self {
+        Importer {
+            format,
+            separators,
+            mapping,
+            table,
+            save_period,
+        }
+    }
+
+    /// Converted file with the same name in `import_path`, `None` for text targets
+    pub fn needs_import(&self, path: &Path) -> bool {
+        self.format.detect(path) != InputFormat::Text
+    }
+
+    pub fn import(&self, path: &Path, import_path: &Path) -> Result<Option<PathBuf>, CoreError> {
+        let format = self.format.detect(path);
+        if format == InputFormat::Text {
+            return Ok(None);
+        }
+
+        let imported_path = import_path.join(path.file_name().unwrap_or_default());
+
+        let file = open_file_r(path)?;
+        let reader = utils::reader_from_file(file);
+        let results_file = Some(utils::open_results_file(&imported_path)?);
+
+        println!("Импорт {} ({})...", path.display(), format);
+
+        let imported = match format {
+            InputFormat::Csv => self.import_csv(reader, path, &results_file),
+            InputFormat::Sql => self.import_sql(reader, path, &results_file),
+            _ => self.import_json(reader, path, &results_file),
+        };
+
+        match imported {
+            Ok(stats) => {
+                println!("Импортировано строк: {}", stats.combos);
+                if stats.ambiguous > 0 {
+                    println!(
+                        "Пропущено строк с разделителем {} в полях: {}",
+                        self.separators.join(" "),
+                        stats.ambiguous
+                    );
+                }
+            }
+            Err(reason) => {
+                return Err(CoreError::Import {
+                    path: path.to_owned(),
+                    reason,
+                })
+            }
+        }
+
+        Ok(Some(imported_path))
+    }
+
+    fn import_csv(
+        &self,
+        mut reader: impl BufRead,
+        path: &Path,
+        results_file: &Option<File>,
+    ) -> Result<ImportStats, String> {
+        let mut results: Vec<String> = Vec::with_capacity(self.save_period);
+        let mut stats = ImportStats::default();
+
+        // the dialect is detected by the first line, the CSV reader reads it again
+        let mut first = String::new();
+        if reader
+            .read_line(&mut first)
+            .map_err(|err| err.to_string())?
+            == 0
+        {
+            return Ok(stats);
+        }
+        let first = first.strip_prefix('\u{feff}').unwrap_or(&first).to_owned();
+        let dialect = CsvDialect::detect(first.trim_end_matches(['\r', '\n']));
+        let mut records = dialect
+            .reader(io::Cursor::new(first).chain(reader))
+            .into_records();
+
+        let first_row = match records.next() {
+            Some(Ok(first_row)) => first_row,
+            Some(Err(err)) => return Err(err.to_string()),
+            None => return Ok(stats),
+        };
+        let names: Vec<String> = first_row.iter().map(str::to_owned).collect();
+        let header = self.is_header(&names);
+        let columns = self.columns(if header { Some(&names) } else { None })?;
+        let first_row = if header { None } else { Some(Ok(first_row)) };
+
+        for row in first_row.into_iter().chain(records) {
+            let row = match row {
+                Ok(row) => row,
+                Err(err) => {
+                    eprintln!(
+                        "Can't read combo on line {} in file {}. {}",
+                        err.position().map(csv::Position::line).unwrap_or_default(),
+                        path.display(),
+                        err
+                    );
+                    if err.is_io_error() {
+                        break;
+                    }
+                    continue;
+                }
+            };
+
+            match self.combo_of(&columns, |column| row.get(column)) {
+                RowCombo::Combo(combo) => {
+                    results.push(combo);
+                    stats.combos += 1;
+                }
+                RowCombo::Ambiguous => stats.ambiguous += 1,
+                RowCombo::Missing => (),
+            }
+
+            if results.len() == self.save_period {
+                if let Err(e) = utils::save_results(&mut results, results_file, None) {
+                    eprintln!("Couldn't write to file: {}", e);
+                }
+            }
+        }
+
+        if let Err(e) = utils::save_results(&mut results, results_file, None) {
+            eprintln!("Couldn't write to file: {}", e);
+        }
+
+        Ok(stats)
+    }
+
+    /// JSON Lines or a top-level array of objects, which is read without collecting it
+    fn import_json(
+        &self,
+        mut reader: impl BufRead,
+        path: &Path,
+        results_file: &Option<File>,
+    ) -> Result<ImportStats, String> {
+        let mut results: Vec<String> = Vec::with_capacity(self.save_period);
+        let mut stats = ImportStats::default();
+        let mut keys: Option<(Vec<String>, Vec<ColumnRef>)> = None;
+
+        let array = starts_array(&mut reader).map_err(|err| err.to_string())?;
+
+        let mut add = |object: JsonObject| -> Result<(), String> {
+            // keys of the first object are the header, mapped keys may be missing in it
+            if keys.is_none() {
+                let mut names: Vec<String> = object.0.iter().map(|(key, _)| key.clone()).collect();
+                for mapping in &self.mapping {
+                    if !names
+                        .iter()
+                        .any(|name| name.eq_ignore_ascii_case(&mapping.column))
+                    {
+                        names.push(mapping.column.clone());
+                    }
+                }
+                let columns = self.columns(Some(&names))?;
+                keys = Some((names, columns));
+            }
+            let (names, columns) = keys.as_ref().unwrap();
+
+            let combo = self.combo_of(columns, |column| {
+                names.get(column).and_then(|name| object.value(name))
+            });
+            match combo {
+                RowCombo::Combo(combo) => {
+                    results.push(combo);
+                    stats.combos += 1;
+                }
+                RowCombo::Ambiguous => stats.ambiguous += 1,
+                RowCombo::Missing => (),
+            }
+
+            if results.len() == self.save_period {
+                if let Err(e) = utils::save_results(&mut results, results_file, None) {
+                    eprintln!("Couldn't write to file: {}", e);
+                }
+            }
+            Ok(())
+        };
+
+        if array {
+            let mut deserializer = serde_json::Deserializer::from_reader(reader);
+            deserializer
+                .deserialize_seq(JsonArrayVisitor(&mut add))
+                .and_then(|_| deserializer.end())
+                .map_err(|err| err.to_string())?;
+        } else {
+            for (i, line) in reader.lines().enumerate() {
+                let line = match line {
+                    Ok(line) => line,
+                    Err(err) => {
+                        eprintln!(
+                            "Can't read combo on line {} in file {}. {}",
+                            i,
+                            path.display(),
+                            err
+                        );
+                        continue;
+                    }
+                };
+                let line = line.trim().trim_start_matches('\u{feff}');
+                if line.is_empty() {
+                    continue;
+                }
+
+                match serde_json::from_str::<JsonObject>(line) {
+                    Ok(object) => add(object)?,
+                    Err(err) => eprintln!(
+                        "Can't read combo on line {} in file {}. {}",
+                        i,
+                        path.display(),
+                        err
+                    ),
+                }
+            }
+        }
+
+        if let Err(e) = utils::save_results(&mut results, results_file, None) {
+            eprintln!("Couldn't write to file: {}", e);
+        }
+
+        Ok(stats)
+    }
+
+    /// Rows of INSERT statements of the table. Columns are named by the INSERT or by CREATE TABLE
+    /// before it, rows of tables without names are taken as `login, password, ...`
+    fn import_sql(
+        &self,
+        reader: impl BufRead,
+        path: &Path,
+        results_file: &Option<File>,
+    ) -> Result<ImportStats, String> {
+        let mut scanner = SqlScanner::default();
+        let mut items: Vec<SqlItem> = Vec::new();
+        let mut tables: HashMap<String, Vec<String>> = HashMap::new();
+        let mut table: Option<String> = None;
+        // columns of the current INSERT when it's into the table
+        let mut columns: Option<Vec<ColumnRef>> = None;
+        // INSERT statements skipped because only --table could tell whether they are into the table
+        let mut unnamed = false;
+
+        let mut results: Vec<String> = Vec::with_capacity(self.save_period);
+        let mut stats = ImportStats::default();
+
+        for (i, line) in reader.lines().enumerate() {
+            let line = match line {
+                Ok(line) => line,
+                Err(err) => {
+                    eprintln!(
+                        "Can't read combo on line {} in file {}. {}",
+                        i,
+                        path.display(),
+                        err
+                    );
+                    continue;
+                }
+            };
+            let line = line.trim_start_matches('\u{feff}');
+            if scanner.is_idle() {
+                let trimmed = line.trim_start();
+                if trimmed.starts_with("--") || trimmed.starts_with('#') {
+                    continue;
+                }
+            }
+
+            scanner.feed(line, &mut items);
+
+            for item in items.drain(..) {
+                match item {
+                    SqlItem::CreateTable {
+                        table: name,
+                        columns,
+                    } => {
+                        tables.insert(name.to_lowercase(), columns);
+                    }
+                    SqlItem::Insert {
+                        table: name,
+                        columns: listed,
+                    } => {
+                        columns = None;
+                        let chosen = match table.as_ref().or(self.table.as_ref()) {
+                            Some(chosen) if !chosen.eq_ignore_ascii_case(&name) => continue,
+                            chosen => chosen.is_some(),
+                        };
+
+                        let header = listed.or_else(|| tables.get(&name.to_lowercase()).cloned());
+                        // without the names of the columns any table would be taken
+                        if !chosen && (header.is_none() || self.maps_by_position()) {
+                            unnamed = true;
+                            continue;
+                        }
+                        match self.columns(header.as_deref()) {
+                            Ok(found) => {
+                                if table.is_none() {
+                                    println!("Таблица: {}", name);
+                                    table = Some(name);
+                                }
+                                columns = Some(found);
+                            }
+                            // without --table the tables are tried until one has the columns
+                            Err(err) if chosen => return Err(err),
+                            Err(_) => (),
+                        }
+                    }
+                    SqlItem::Row(row) => {
+                        let columns = match &columns {
+                            Some(columns) => columns,
+                            None => continue,
+                        };
+                        match self.combo_of(columns, |column| row.get(column).map(String::as_str)) {
+                            RowCombo::Combo(combo) => {
+                                results.push(combo);
+                                stats.combos += 1;
+                            }
+                            RowCombo::Ambiguous => stats.ambiguous += 1,
+                            RowCombo::Missing => (),
+                        }
+
+                        if results.len() == self.save_period {
+                            if let Err(e) = utils::save_results(&mut results, results_file, None) {
+                                eprintln!("Couldn't write to file: {}", e);
+                            }
+                        }
+                    }
+                }
+            }
+        }
+
+        if let Err(e) = utils::save_results(&mut results, results_file, None) {
+            eprintln!("Couldn't write to file: {}", e);
+        }
+
+        if table.is_none() {
+            return Err(match &self.table {
+                Some(table) => format!("no INSERT statements into table {}", table),
+                None if unnamed => String::from(
+                    "columns of the INSERT statements are unknown, set the table with --table",
+                ),
+                None => String::from(
+                    "no INSERT statements into a table with login and password columns, set it with --table",
+                ),
+            });
+        }
+
+        Ok(stats)
+    }
+
+    /// Combo of the row's fields joined with the first separator. Combos are split at the
+    /// first separator, so only the last field may have separators and other rows are skipped
+    fn combo_of<'a>(
+        &self,
+        columns: &[ColumnRef],
+        value: impl Fn(usize) -> Option<&'a str>,
+    ) -> RowCombo {
+        let fields: Vec<&str> = columns
+            .iter()
+            .map(|column| value(column.column).unwrap_or_default())
+            .collect();
+        if fields.iter().take(2).any(|field| field.is_empty()) {
+            return RowCombo::Missing;
+        }
+        let (_, split) = fields.split_last().unwrap();
+        if split.iter().any(|field| {
+            self.separators
+                .iter()
+                .any(|separator| field.contains(separator.as_str()))
+        }) {
+            return RowCombo::Ambiguous;
+        }
+
+        let separator = self.separators.first().map_or(":", String::as_str);
+        let fields: Vec<String> = fields
+            .iter()
+            // combos are lines
+            .map(|field| field.replace(['\r', '\n'], " "))
+            .collect();
+        RowCombo::Combo(fields.join(separator))
+    }
+
+    fn is_header(&self, row: &[String]) -> bool {
+        row.iter().any(|cell| {
+            let cell = cell.trim().to_lowercase();
+            LOGIN_COLUMNS.contains(&cell.as_str())
+                || PASSWORD_COLUMNS.contains(&cell.as_str())
+                || self
+                    .mapping
+                    .iter()
+                    .any(|mapping| mapping.column.to_lowercase() == cell)
+        })
+    }
+
+    /// Login and password are mapped to the positions of the columns, so every table has them
+    fn maps_by_position(&self) -> bool {
+        ["login", "password"].iter().all(|field| {
+            self.mapping
+                .iter()
+                .any(|mapping| mapping.field == *field && mapping.column.parse::<usize>().is_ok())
+        })
+    }
+
+    /// Positions of the combo fields in rows, `header` has names of the columns.
+    /// Login and password go first, the other mapped fields follow them in their order
+    fn columns(&self, header: Option<&[String]>) -> Result<Vec<ColumnRef>, String> {
+        let position = |name: &str| {
+            header.and_then(|header| {
+                header
+                    .iter()
+                    .position(|column| column.trim().eq_ignore_ascii_case(name))
+            })
+        };
+        let guess = |names: &[&str], default: usize| match header {
+            Some(_) => names.iter().find_map(|name| position(name)),
+            None => Some(default),
+        };
+
+        let mut columns = Vec::with_capacity(self.mapping.len().max(2));
+        for field in ["login", "password"] {
+            if self.mapping.iter().any(|mapping| mapping.field == field) {
+                continue;
+            }
+            let found = match field {
+                "login" => guess(&LOGIN_COLUMNS, 0),
+                _ => guess(&PASSWORD_COLUMNS, 1),
+            };
+            match found {
+                Some(column) => columns.push(ColumnRef {
+                    field: field.to_owned(),
+                    column,
+                }),
+                None => {
+                    return Err(format!(
+                        "no {} column, set it with --map {}=<column>",
+                        field, field
+                    ))
+                }
+            }
+        }
+
+        for mapping in &self.mapping {
+            let column = match mapping.column.parse::<usize>() {
+                Ok(number) if number > 0 => number - 1,
+                _ => match position(&mapping.column) {
+                    Some(column) => column,
+                    None => return Err(format!("no column {}", mapping.column)),
+                },
+            };
+            columns.push(ColumnRef {
+                field: mapping.field.clone(),
+                column,
+            });
+        }
+
+        columns.sort_by_key(|column| match column.field.as_str() {
+            "login" => 0,
+            "password" => 1,
+            _ => 2,
+        });
+
+        Ok(columns)
+    }
+}
+
+/// Combo field and the position of its column
+#[derive(Debug)]
+struct ColumnRef {
+    field: String,
+    column: usize,
+}
+
+/// Combo of a row
+enum RowCombo {
+    Combo(String),
+    /// The row has no login or password
+    Missing,
+    /// A field of the row has a separator of the combos, so the combo would be split wrong
+    Ambiguous,
+}
+
+/// Numbers of the imported rows and of the rows skipped because of separators in their fields
+#[derive(Debug, Default)]
+struct ImportStats {
+    combos: usize,
+    ambiguous: usize,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct CsvDialect {
+    delimiter: u8,
+    quote: u8,
+}
+
+impl CsvDialect {
+    /// The delimiter is the most frequent one in the first line, ties go to the earlier one
+    /// of `CSV_DELIMITERS`. Values are quoted with `'` when the line has no `"`
+    fn detect(line: &str) -> CsvDialect {
+        let quote = if !line.contains('"') && line.contains('\'') {
+            b'\''
+        } else {
+            b'"'
+        };
+
+        // `max_by_key` takes the last of equal ones
+        let delimiter = CSV_DELIMITERS
+            .iter()
+            .rev()
+            .copied()
+            .max_by_key(|&delimiter| CsvDialect { delimiter, quote }.values(line))
+            .unwrap_or(b',');
+
+        CsvDialect { delimiter, quote }
+    }
+
+    /// Records may have different numbers of values, quoted values may have newlines
+    fn reader<R: io::Read>(&self, reader: R) -> csv::Reader<R> {
+        csv::ReaderBuilder::new()
+            .delimiter(self.delimiter)
+            .quote(self.quote)
+            .has_headers(false)
+            .flexible(true)
+            .from_reader(reader)
+    }
+
+    /// Number of values in the line
+    fn values(&self, line: &str) -> usize {
+        match self.reader(line.as_bytes()).records().next() {
+            Some(Ok(row)) => row.len(),
+            _ => 0,
+        }
+    }
+}
+
+/// Whether the JSON is a top-level array, whitespace and the BOM before it are skipped
+fn starts_array(reader: &mut impl BufRead) -> io::Result<bool> {
+    loop {
+        let buf = reader.fill_buf()?;
+        if buf.is_empty() {
+            return Ok(false);
+        }
+
+        let bom = "\u{feff}".as_bytes();
+        let start = if buf.starts_with(bom) { bom.len() } else { 0 };
+        match buf[start..].iter().position(|c| !c.is_ascii_whitespace()) {
+            Some(pos) => {
+                let array = buf[start + pos] == b'[';
+                reader.consume(start + pos);
+                return Ok(array);
+            }
+            None => {
+                let len = buf.len();
+                reader.consume(len);
+            }
+        }
+    }
+}
+
+/// Keys and values of a JSON object in their order, nested values are kept as JSON text
+struct JsonObject(Vec<(String, String)>);
+
+impl JsonObject {
+    fn value(&self, key: &str) -> Option<&str> {
+        self.0
+            .iter()
+            .find(|(name, _)| name == key)
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+impl<'de> Deserialize<'de> for JsonObject {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_map(JsonObjectVisitor)
+    }
+}
+
+struct JsonObjectVisitor;
+
+impl<'de> Visitor<'de> for JsonObjectVisitor {
+    type Value = JsonObject;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "a JSON object")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<JsonObject, A::Error> {
+        let mut object = Vec::new();
+        while let Some((key, value)) = map.next_entry::<String, Value>()? {
+            let value = match value {
+                Value::String(value) => value,
+                Value::Null => String::new(),
+                value => value.to_string(),
+            };
+            object.push((key, value));
+        }
+        Ok(JsonObject(object))
+    }
+}
+
+/// Passes objects of a JSON array to the function one by one, so the array isn't collected
+struct JsonArrayVisitor<F>(F);
+
+impl<'de, F: FnMut(JsonObject) -> Result<(), String>> Visitor<'de> for JsonArrayVisitor<F> {
+    type Value = ();
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "an array of JSON objects")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(mut self, mut seq: A) -> Result<(), A::Error> {
+        while let Some(object) = seq.next_element::<JsonObject>()? {
+            (self.0)(object).map_err(de::Error::custom)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::{CsvDialect, Importer, InputFormat};
+    use crate::core::utils;
+
+    fn import(name: &str, file_name: &str, content: &str) -> Vec<String> {
+        let dir = utils::test_dir(name);
+        let target = dir.join(file_name);
+        fs::write(&target, content).unwrap();
+
+        let importer = Importer::new(
+            InputFormat::Auto,
+            vec![":".to_owned(), ";".to_owned()],
+            Vec::new(),
+            None,
+            2,
+        );
+        let imported = importer.import(&target, &dir.join("imported")).unwrap();
+        let lines = utils::read_decoded_lines(&imported.unwrap()).unwrap();
+
+        fs::remove_dir_all(&dir).unwrap();
+        lines
+    }
+
+    #[test]
+    fn delimiter_ties_go_to_comma() {
+        assert_eq!(CsvDialect::detect("a,b;c").delimiter, b',');
+        assert_eq!(CsvDialect::detect("a;b,c").delimiter, b',');
+        assert_eq!(CsvDialect::detect("a|b\tc").delimiter, b'\t');
+        assert_eq!(CsvDialect::detect("login").delimiter, b',');
+        assert_eq!(CsvDialect::detect("a;b;c,d").delimiter, b';');
+    }
+
+    #[test]
+    fn json_arrays_are_imported() {
+        let lines = import(
+            "import_json_array",
+            "users.json",
+            "[\n  {\"email\": \"a@b.c\", \"password\": \"qwerty\"},\n  {\"email\": \"d@e.f\", \"password\": \"12345\", \"id\": 2},\n  {\"email\": null, \"password\": \"x\"}\n]\n",
+        );
+        assert_eq!(lines, ["a@b.c:qwerty", "d@e.f:12345"]);
+    }
+
+    #[test]
+    fn fields_with_separators_are_skipped() {
+        let lines = import(
+            "import_separators",
+            "users.csv",
+            "email,password\nuser:name@b.c,pass\na@b.c,pa:ss\nd;e@f.g,word\n\"h@i.j\",\"multi\nline\"\n",
+        );
+        assert_eq!(lines, ["a@b.c:pa:ss", "h@i.j:multi line"]);
+    }
+}
@@ -1,5 +1,20 @@
+pub mod bloom_filter;
+pub mod combo;
 #[allow(clippy::module_inception)]
 pub mod core;
+pub mod hash_index;
+pub mod importer;
 pub mod lines_processor;
+pub mod output_format;
+pub mod results_layout;
+pub mod set_op;
+pub mod shard_mode;
+pub mod sharded_set;
+pub mod slice;
+pub mod sort_key;
+pub mod sorted_lines;
+pub mod split_key;
+pub mod sql_dump;
+pub mod targets;
 pub mod task;
 pub mod utils;
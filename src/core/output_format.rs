@@ -0,0 +1,190 @@
+use std::str::FromStr;
+
+/// Name of the fields which are not named by the schema
+pub const EXTRA_FIELD: &str = "extra";
+
+/// How the tasks which rebuild combos write them
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub enum OutputFormat {
+    /// Fields joined with the separator
+    #[default]
+    Combo,
+    /// Text with `{field}` placeholders like `{login};{password}`
+    Template(Vec<TemplatePart>),
+    Csv,
+    Jsonl,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TemplatePart {
+    Text(String),
+    Field(String),
+}
+
+impl OutputFormat {
+    /// Names used by the template placeholders
+    pub fn fields(&self) -> Vec<&str> {
+        match self {
+            OutputFormat::Template(parts) => parts
+                .iter()
+                .filter_map(|part| match part {
+                    TemplatePart::Field(name) => Some(name.as_str()),
+                    TemplatePart::Text(_) => None,
+                })
+                .collect(),
+            _ => Vec::new(),
+        }
+    }
+
+    /// Writes fields of the combo, `record` has the fields with their names in the output order.
+    /// `login` and `password` are also available by these names when the schema calls them differently
+    pub fn write(
+        &self,
+        record: &[(&str, &str)],
+        login: &str,
+        password: &str,
+        separator: &str,
+    ) -> String {
+        match self {
+            OutputFormat::Combo => record
+                .iter()
+                .map(|(_, value)| *value)
+                .collect::<Vec<&str>>()
+                .join(separator),
+            OutputFormat::Template(parts) => {
+                let mut line = String::new();
+                for part in parts {
+                    match part {
+                        TemplatePart::Text(text) => line.push_str(text),
+                        TemplatePart::Field(name) => match name.as_str() {
+                            "login" => line.push_str(login),
+                            "password" => line.push_str(password),
+                            EXTRA_FIELD => line.push_str(
+                                &record
+                                    .iter()
+                                    .filter(|(field, _)| *field == EXTRA_FIELD)
+                                    .map(|(_, value)| *value)
+                                    .collect::<Vec<&str>>()
+                                    .join(separator),
+                            ),
+                            name => {
+                                if let Some((_, value)) =
+                                    record.iter().find(|(field, _)| *field == name)
+                                {
+                                    line.push_str(value);
+                                }
+                            }
+                        },
+                    }
+                }
+                line
+            }
+            OutputFormat::Csv => record
+                .iter()
+                .map(|(_, value)| csv_value(value))
+                .collect::<Vec<String>>()
+                .join(","),
+            OutputFormat::Jsonl => {
+                let mut line = String::from("{");
+                let mut extra: Vec<String> = Vec::new();
+                for (field, value) in record {
+                    if *field == EXTRA_FIELD {
+                        extra.push(json_string(value));
+                        continue;
+                    }
+                    if line.len() > 1 {
+                        line.push(',');
+                    }
+                    line.push_str(&json_string(field));
+                    line.push(':');
+                    line.push_str(&json_string(value));
+                }
+                if !extra.is_empty() {
+                    if line.len() > 1 {
+                        line.push(',');
+                    }
+                    line.push_str(&json_string(EXTRA_FIELD));
+                    line.push_str(":[");
+                    line.push_str(&extra.join(","));
+                    line.push(']');
+                }
+                line.push('}');
+                line
+            }
+        }
+    }
+}
+
+impl FromStr for OutputFormat {
+    type Err = String;
+
+    /// `combo`, `csv`, `jsonl` or a template, `\t` in templates is the tab character
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "combo" => return Ok(OutputFormat::Combo),
+            "csv" => return Ok(OutputFormat::Csv),
+            "jsonl" => return Ok(OutputFormat::Jsonl),
+            _ => (),
+        }
+
+        let template = s.replace("\\t", "\t");
+        let mut parts: Vec<TemplatePart> = Vec::new();
+        let mut rest = template.as_str();
+        while let Some(start) = rest.find('{') {
+            if start > 0 {
+                parts.push(TemplatePart::Text(rest[..start].to_owned()));
+            }
+            let end = match rest[start..].find('}') {
+                Some(end) => start + end,
+                None => return Err(String::from("template has { without }")),
+            };
+            let name = rest[start + 1..end].trim().to_lowercase();
+            if name.is_empty() {
+                return Err(String::from("template has an empty {}"));
+            }
+            parts.push(TemplatePart::Field(name));
+            rest = &rest[end + 1..];
+        }
+        if !rest.is_empty() {
+            parts.push(TemplatePart::Text(rest.to_owned()));
+        }
+
+        if !parts
+            .iter()
+            .any(|part| matches!(part, TemplatePart::Field(_)))
+        {
+            return Err(String::from(
+                "must be csv, jsonl or a template with fields like {login}:{password}",
+            ));
+        }
+
+        Ok(OutputFormat::Template(parts))
+    }
+}
+
+/// Value quoted when it has commas, quotes or newlines, quotes inside are doubled
+fn csv_value(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_owned()
+    }
+}
+
+fn json_string(value: &str) -> String {
+    let mut json = String::with_capacity(value.len() + 2);
+    json.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => json.push_str("\\\""),
+            '\\' => json.push_str("\\\\"),
+            '\n' => json.push_str("\\n"),
+            '\r' => json.push_str("\\r"),
+            '\t' => json.push_str("\\t"),
+            c if c.is_control() => json.push_str(&format!("\\u{:04x}", c as u32)),
+            c => json.push(c),
+        }
+    }
+    json.push('"');
+    json
+}
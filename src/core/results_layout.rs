@@ -0,0 +1,221 @@
+use std::{
+    collections::HashMap,
+    fmt, fs,
+    path::{Component, Path, PathBuf},
+    str::FromStr,
+};
+
+use clap::{ArgEnum, PossibleValue};
+
+use super::utils;
+use crate::errors::core_error::CoreError;
+
+/// What is done when results of different targets would get the same name
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ArgEnum)]
+pub enum CollisionPolicy {
+    Mirror,
+    #[default]
+    Suffix,
+    Overwrite,
+    Fail,
+}
+
+impl CollisionPolicy {
+    pub fn possible_values() -> impl Iterator<Item = PossibleValue<'static>> {
+        CollisionPolicy::value_variants()
+            .iter()
+            .filter_map(ArgEnum::to_possible_value)
+    }
+}
+
+impl fmt::Display for CollisionPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            CollisionPolicy::Mirror => write!(f, "повторить папки"),
+            CollisionPolicy::Suffix => write!(f, "добавить номер"),
+            CollisionPolicy::Overwrite => write!(f, "перезаписать"),
+            CollisionPolicy::Fail => write!(f, "остановиться"),
+        }
+    }
+}
+
+impl FromStr for CollisionPolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let policy = match s {
+            "mirror" => CollisionPolicy::Mirror,
+            "suffix" => CollisionPolicy::Suffix,
+            "overwrite" => CollisionPolicy::Overwrite,
+            "fail" => CollisionPolicy::Fail,
+            _ => return Err("Такого варианта нет".to_owned()),
+        };
+
+        Ok(policy)
+    }
+}
+
+/// Places result files of the targets inside of the results directory,
+/// so targets with the same names don't write into the same files
+#[derive(Debug, Clone, Default)]
+pub struct ResultsLayout {
+    dirs: HashMap<PathBuf, PathBuf>,
+    names: HashMap<PathBuf, PathBuf>,
+    overwrite: bool,
+}
+
+impl ResultsLayout {
+    /// With `mirror` results of the files found in directories repeat their structure,
+    /// the targets whose results still collide are handled by `policy`
+    pub fn new(
+        targets: &[PathBuf],
+        rel_dirs: HashMap<PathBuf, PathBuf>,
+        mirror: bool,
+        policy: CollisionPolicy,
+    ) -> Result<Self, CoreError> {
+        let mut layout = ResultsLayout {
+            dirs: if mirror { rel_dirs } else { HashMap::new() },
+            names: HashMap::new(),
+            overwrite: policy == CollisionPolicy::Overwrite,
+        };
+
+        // file names are case-insensitive on Windows
+        let mut groups: Vec<Vec<&PathBuf>> = Vec::new();
+        let mut group_nums: HashMap<(PathBuf, String), usize> = HashMap::new();
+        for target in targets {
+            let key = (
+                layout.dirs.get(target).cloned().unwrap_or_default(),
+                target
+                    .file_name()
+                    .unwrap_or_default()
+                    .to_string_lossy()
+                    .to_lowercase(),
+            );
+            let group_num = *group_nums.entry(key).or_insert_with(|| {
+                groups.push(Vec::new());
+                groups.len() - 1
+            });
+            groups[group_num].push(target);
+        }
+
+        for group in groups.iter().filter(|group| group.len() > 1) {
+            match policy {
+                CollisionPolicy::Fail => {
+                    return Err(CoreError::ResultsCollision {
+                        first: group[0].clone(),
+                        second: group[1].clone(),
+                    })
+                }
+                CollisionPolicy::Suffix => {
+                    for (i, target) in group.iter().enumerate().skip(1) {
+                        let mut name = target.file_stem().unwrap_or_default().to_owned();
+                        name.push(format!(" ({})", i + 1));
+                        if let Some(extension) = target.extension() {
+                            name.push(".");
+                            name.push(extension);
+                        }
+                        layout
+                            .names
+                            .insert((*target).clone(), target.with_file_name(name));
+                    }
+                }
+                CollisionPolicy::Mirror => {
+                    let common = common_parent(group);
+                    for target in group {
+                        let parent = target.parent().unwrap_or_else(|| Path::new(""));
+                        let rel_dir: PathBuf = parent
+                            .strip_prefix(&common)
+                            .unwrap_or(parent)
+                            .components()
+                            .filter(|component| matches!(component, Component::Normal(_)))
+                            .collect();
+                        let dir = layout.dirs.entry((*target).clone()).or_default();
+                        dir.push(rel_dir);
+                    }
+                }
+                CollisionPolicy::Overwrite => (),
+            }
+        }
+
+        Ok(layout)
+    }
+
+    pub fn results_dir(&self, file_path: &Path, results_path: &Path) -> PathBuf {
+        match self.dirs.get(file_path) {
+            Some(rel_dir) => results_path.join(rel_dir),
+            None => results_path.to_owned(),
+        }
+    }
+
+    /// Own directory of the target for tasks which write many files for every target
+    pub fn file_dir(&self, file_path: &Path, results_path: &Path) -> PathBuf {
+        let dir = self
+            .results_dir(file_path, results_path)
+            .join(self.name(file_path).file_name().unwrap_or_default());
+        // results of the previous target with the same name
+        if self.overwrite && dir.exists() {
+            if let Err(err) = fs::remove_dir_all(&dir) {
+                eprintln!("Can't overwrite {}. {}", dir.display(), err);
+            }
+        }
+        dir
+    }
+
+    pub fn build_results_path(
+        &self,
+        file_path: &Path,
+        results_path: &Path,
+        suffix: &str,
+    ) -> PathBuf {
+        let path = utils::build_results_path(
+            self.name(file_path),
+            self.results_dir(file_path, results_path).as_path(),
+            suffix,
+        );
+        if self.overwrite && path.exists() {
+            if let Err(err) = fs::remove_file(&path) {
+                eprintln!("Can't overwrite {}. {}", path.display(), err);
+            }
+        }
+        path
+    }
+
+    /// Results of the `imported` file are placed and named like results of the `target`
+    pub fn replace_target(&mut self, target: &Path, imported: &Path) {
+        if let Some(dir) = self.dirs.remove(target) {
+            self.dirs.insert(imported.to_owned(), dir);
+        }
+        let name = self.name(target).to_owned();
+        self.names.remove(target);
+        self.names.insert(imported.to_owned(), name);
+    }
+
+    fn name<'a>(&'a self, file_path: &'a Path) -> &'a Path {
+        self.names
+            .get(file_path)
+            .map(PathBuf::as_path)
+            .unwrap_or(file_path)
+    }
+}
+
+fn common_parent(paths: &[&PathBuf]) -> PathBuf {
+    let mut common: Vec<Component> = match paths.first().and_then(|path| path.parent()) {
+        Some(parent) => parent.components().collect(),
+        None => return PathBuf::new(),
+    };
+
+    for path in &paths[1..] {
+        let components: Vec<Component> = path
+            .parent()
+            .map(|parent| parent.components().collect())
+            .unwrap_or_default();
+        let same = common
+            .iter()
+            .zip(&components)
+            .take_while(|(a, b)| a == b)
+            .count();
+        common.truncate(same);
+    }
+
+    common.into_iter().collect()
+}
@@ -0,0 +1,113 @@
+use std::{fmt, str::FromStr};
+
+use clap::{ArgEnum, PossibleValue};
+
+/// Set operation performed by `Task::Compare` between targets and `--with` files.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ArgEnum)]
+pub enum SetOp {
+    /// Lines of the target which are absent in `--with`
+    #[default]
+    Diff,
+    /// Lines of the target which are present in `--with`
+    Intersect,
+    /// Lines present only in the target or only in `--with`
+    Symdiff,
+    /// Lines of both sides without duplicates
+    Union,
+}
+
+impl SetOp {
+    /// Part of the result file name, substituted into `{op}` of the task suffix
+    pub fn to_suffix(self) -> &'static str {
+        match self {
+            SetOp::Diff => "uniq",
+            SetOp::Intersect => "common",
+            SetOp::Symdiff => "symdiff",
+            SetOp::Union => "union",
+        }
+    }
+
+    /// Operations whose results are written, `emit` picks outputs of diff and intersect
+    pub fn outputs(self, emit: Option<Emit>) -> &'static [SetOp] {
+        let emit = match (self, emit) {
+            (SetOp::Symdiff, _) => return &[SetOp::Symdiff],
+            (SetOp::Union, _) => return &[SetOp::Union],
+            (_, Some(emit)) => emit,
+            (SetOp::Diff, None) => Emit::Unique,
+            (SetOp::Intersect, None) => Emit::Matched,
+        };
+
+        match emit {
+            Emit::Unique => &[SetOp::Diff],
+            Emit::Matched => &[SetOp::Intersect],
+            Emit::Both => &[SetOp::Diff, SetOp::Intersect],
+        }
+    }
+
+    pub fn possible_values() -> impl Iterator<Item = PossibleValue<'static>> {
+        SetOp::value_variants()
+            .iter()
+            .filter_map(ArgEnum::to_possible_value)
+    }
+}
+
+impl fmt::Display for SetOp {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            SetOp::Diff => write!(f, "Разность"),
+            SetOp::Intersect => write!(f, "Пересечение"),
+            SetOp::Symdiff => write!(f, "Симметрическая разность"),
+            SetOp::Union => write!(f, "Объединение"),
+        }
+    }
+}
+
+impl FromStr for SetOp {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let op = match s {
+            "diff" => SetOp::Diff,
+            "intersect" => SetOp::Intersect,
+            "symdiff" => SetOp::Symdiff,
+            "union" => SetOp::Union,
+            _ => return Err("Такой операции нет".to_owned()),
+        };
+
+        Ok(op)
+    }
+}
+
+/// Which lines of the target are written by compare
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ArgEnum)]
+pub enum Emit {
+    /// Lines which are absent in `--with`
+    Unique,
+    /// Lines which are present in `--with`
+    Matched,
+    /// Both of them into separate files
+    Both,
+}
+
+impl Emit {
+    pub fn possible_values() -> impl Iterator<Item = PossibleValue<'static>> {
+        Emit::value_variants()
+            .iter()
+            .filter_map(ArgEnum::to_possible_value)
+    }
+}
+
+impl FromStr for Emit {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let emit = match s {
+            "unique" => Emit::Unique,
+            "matched" => Emit::Matched,
+            "both" => Emit::Both,
+            _ => return Err("Такого вывода нет".to_owned()),
+        };
+
+        Ok(emit)
+    }
+}
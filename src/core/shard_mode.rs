@@ -0,0 +1,50 @@
+use std::{fmt, str::FromStr};
+
+use clap::{ArgEnum, PossibleValue};
+
+/// How lines are distributed between shards
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ArgEnum)]
+pub enum ShardMode {
+    #[default]
+    RoundRobin,
+    Hash,
+}
+
+impl ShardMode {
+    /// 0-based shard of the `line_num`-th line, identical lines always get the same shard in hash mode
+    pub fn shard(self, combo: &str, line_num: usize, shards_n: usize) -> usize {
+        match self {
+            ShardMode::RoundRobin => line_num % shards_n,
+            ShardMode::Hash => (seahash::hash(combo.as_bytes()) % shards_n as u64) as usize,
+        }
+    }
+
+    pub fn possible_values() -> impl Iterator<Item = PossibleValue<'static>> {
+        ShardMode::value_variants()
+            .iter()
+            .filter_map(ArgEnum::to_possible_value)
+    }
+}
+
+impl fmt::Display for ShardMode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ShardMode::RoundRobin => write!(f, "по очереди"),
+            ShardMode::Hash => write!(f, "по хешу строки"),
+        }
+    }
+}
+
+impl FromStr for ShardMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mode = match s {
+            "round-robin" => ShardMode::RoundRobin,
+            "hash" => ShardMode::Hash,
+            _ => return Err("Такого режима нет".to_owned()),
+        };
+
+        Ok(mode)
+    }
+}
@@ -0,0 +1,115 @@
+use std::sync::Mutex;
+
+use super::utils::{NoHashMap, NoHashSet};
+
+const SHARDS_COUNT: usize = 64;
+
+/// Collection of line hashes split into independently locked shards,
+/// so many threads can insert and remove hashes at once
+pub struct Sharded<T> {
+    shards: Vec<Mutex<T>>,
+}
+
+pub type ShardedHashSet = Sharded<NoHashSet>;
+
+/// Hash to index of the first file where the line was found
+pub type ShardedHashMap = Sharded<NoHashMap<usize>>;
+
+impl<T: Default> Sharded<T> {
+    pub fn new() -> Self {
+        Sharded {
+            shards: (0..SHARDS_COUNT)
+                .map(|_| Mutex::new(T::default()))
+                .collect(),
+        }
+    }
+
+    // middle bits are used because low bits pick the bucket inside of the shard
+    // and the top 7 bits are the control tag, keys of a shard must still differ in both
+    fn shard(&self, hash: u64) -> &Mutex<T> {
+        &self.shards[((hash >> 32) as usize) & (SHARDS_COUNT - 1)]
+    }
+}
+
+impl ShardedHashSet {
+    pub fn insert(&self, hash: u64) -> bool {
+        self.shard(hash).lock().unwrap().insert(hash)
+    }
+
+    pub fn contains(&self, hash: &u64) -> bool {
+        self.shard(*hash).lock().unwrap().contains(hash)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.shards.iter().all(|s| s.lock().unwrap().is_empty())
+    }
+
+    /// Moves the hash into `matched` with the index of the file when it's in the set,
+    /// with `keep_min` a moved hash gets the smallest index of the files which have it.
+    /// Shards of both collections are locked at once, so other threads never see the hash in neither
+    pub fn move_to(&self, hash: u64, matched: &ShardedHashMap, file_num: usize, keep_min: bool) {
+        let mut shard = self.shard(hash).lock().unwrap();
+        let mut matched_shard = matched.shard(hash).lock().unwrap();
+        if shard.remove(&hash) {
+            matched_shard.insert(hash, file_num);
+        } else if let Some(entry) = matched_shard.get_mut(&hash) {
+            if keep_min && *entry > file_num {
+                *entry = file_num;
+            }
+        }
+    }
+
+    /// Removes hashes matching `filter` and returns them
+    pub fn take_matching(&self, filter: impl Fn(&u64) -> bool) -> Vec<u64> {
+        let mut taken = Vec::new();
+        for shard in self.shards.iter() {
+            shard.lock().unwrap().retain(|hash| {
+                if filter(hash) {
+                    taken.push(*hash);
+                    false
+                } else {
+                    true
+                }
+            });
+        }
+        taken
+    }
+}
+
+impl ShardedHashMap {
+    /// Keeps the smallest file index, so the result doesn't depend on threads order
+    pub fn insert_min(&self, hash: u64, file_num: usize) {
+        let mut shard = self.shard(hash).lock().unwrap();
+        let entry = shard.entry(hash).or_insert(file_num);
+        if *entry > file_num {
+            *entry = file_num;
+        }
+    }
+
+    pub fn get(&self, hash: &u64) -> Option<usize> {
+        self.shard(*hash).lock().unwrap().get(hash).copied()
+    }
+
+    pub fn contains(&self, hash: &u64) -> bool {
+        self.shard(*hash).lock().unwrap().contains_key(hash)
+    }
+
+    pub fn len(&self) -> usize {
+        self.shards.iter().map(|s| s.lock().unwrap().len()).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.shards.iter().all(|s| s.lock().unwrap().is_empty())
+    }
+
+    /// Number of hashes attributed to each of `files_count` files
+    pub fn count_by_file(&self, files_count: usize) -> Vec<usize> {
+        let mut counts = vec![0usize; files_count];
+        for shard in self.shards.iter() {
+            for &file_num in shard.lock().unwrap().values() {
+                counts[file_num] += 1;
+            }
+        }
+        counts
+    }
+}
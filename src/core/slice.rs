@@ -0,0 +1,65 @@
+use std::fmt;
+
+use super::utils;
+
+/// Part of the target which is cut out by the slice task. Line numbers start from 1,
+/// lines ranges include both ends, ranges without the end go to the end of the file
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Slice {
+    Head(usize),
+    Tail(usize),
+    Lines {
+        from: usize,
+        to: Option<usize>,
+    },
+    /// Lines which start in the byte range, its end is excluded so neighbouring ranges don't overlap
+    Bytes {
+        from: usize,
+        to: Option<usize>,
+    },
+}
+
+impl Slice {
+    /// Parses `from-to` or `from-` lines range
+    pub fn parse_lines(range: &str) -> Option<Slice> {
+        let (from, to) = parse_range(range, |s| s.trim().parse::<usize>().ok())?;
+        if from == 0 {
+            return None;
+        }
+        Some(Slice::Lines { from, to })
+    }
+
+    /// Parses `from-to` or `from-` bytes range, sizes may have units like `50GB-51GB`
+    pub fn parse_bytes(range: &str) -> Option<Slice> {
+        let (from, to) = parse_range(range, utils::parse_size)?;
+        Some(Slice::Bytes { from, to })
+    }
+}
+
+fn parse_range(range: &str, parse: fn(&str) -> Option<usize>) -> Option<(usize, Option<usize>)> {
+    let (from, to) = range.split_once('-')?;
+    let from = parse(from)?;
+    let to = if to.trim().is_empty() {
+        None
+    } else {
+        Some(parse(to)?)
+    };
+
+    match to {
+        Some(to) if to < from => None,
+        _ => Some((from, to)),
+    }
+}
+
+impl fmt::Display for Slice {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Slice::Head(n) => write!(f, "первые {} строк", n),
+            Slice::Tail(n) => write!(f, "последние {} строк", n),
+            Slice::Lines { from, to: Some(to) } => write!(f, "строки с {} по {}", from, to),
+            Slice::Lines { from, to: None } => write!(f, "строки с {} до конца", from),
+            Slice::Bytes { from, to: Some(to) } => write!(f, "байты с {} до {}", from, to),
+            Slice::Bytes { from, to: None } => write!(f, "байты с {} до конца", from),
+        }
+    }
+}
@@ -0,0 +1,197 @@
+use std::{cmp::Ordering, fmt, str::FromStr};
+
+use clap::{ArgEnum, PossibleValue};
+
+use super::{combo::ComboParser, utils};
+
+/// Part of the combo lines are compared by
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ArgEnum)]
+pub enum SortKey {
+    #[default]
+    Line,
+    Login,
+    Domain,
+    Password,
+    Length,
+}
+
+impl SortKey {
+    pub fn possible_values() -> impl Iterator<Item = PossibleValue<'static>> {
+        SortKey::value_variants()
+            .iter()
+            .filter_map(ArgEnum::to_possible_value)
+    }
+}
+
+impl fmt::Display for SortKey {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            SortKey::Line => write!(f, "строка"),
+            SortKey::Login => write!(f, "логин"),
+            SortKey::Domain => write!(f, "домен"),
+            SortKey::Password => write!(f, "пароль"),
+            SortKey::Length => write!(f, "длина строки"),
+        }
+    }
+}
+
+impl FromStr for SortKey {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let key = match s {
+            "line" => SortKey::Line,
+            "login" => SortKey::Login,
+            "domain" => SortKey::Domain,
+            "password" => SortKey::Password,
+            "length" => SortKey::Length,
+            _ => return Err("Такого ключа нет".to_owned()),
+        };
+
+        Ok(key)
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct SortOptions {
+    pub key: SortKey,
+    pub ignore_case: bool,
+    pub numeric: bool,
+    pub reverse: bool,
+    pub parser: ComboParser,
+    /// Schema field which is used as the key instead of `key`
+    pub field: Option<String>,
+}
+
+impl SortOptions {
+    pub fn entry(&self, line: String) -> SortEntry {
+        let key = match self.key {
+            SortKey::Length => KeyValue::Number(line.chars().count() as f64),
+            key => {
+                let text = match &self.field {
+                    Some(field) => self
+                        .parser
+                        .split(&line)
+                        .and_then(|combo| self.parser.field(&combo, field))
+                        .unwrap_or(""),
+                    None => key_text(key, &self.parser, &line),
+                };
+                if self.numeric {
+                    KeyValue::Number(leading_number(text))
+                } else if self.ignore_case {
+                    KeyValue::Text(text.to_lowercase())
+                } else {
+                    KeyValue::Text(text.to_owned())
+                }
+            }
+        };
+
+        SortEntry {
+            key,
+            line,
+            reverse: self.reverse,
+        }
+    }
+}
+
+/// Sort key computed once per line
+#[derive(Debug)]
+pub enum KeyValue {
+    Text(String),
+    Number(f64),
+}
+
+impl KeyValue {
+    fn cmp(&self, other: &KeyValue) -> Ordering {
+        match (self, other) {
+            (KeyValue::Text(a), KeyValue::Text(b)) => utils::cmp_encoded(a, b),
+            (KeyValue::Number(a), KeyValue::Number(b)) => a.total_cmp(b),
+            (KeyValue::Number(_), KeyValue::Text(_)) => Ordering::Less,
+            (KeyValue::Text(_), KeyValue::Number(_)) => Ordering::Greater,
+        }
+    }
+}
+
+/// Line with its key, lines with equal keys are ordered by the whole line
+#[derive(Debug)]
+pub struct SortEntry {
+    pub key: KeyValue,
+    pub line: String,
+    reverse: bool,
+}
+
+impl SortEntry {
+    pub fn same_key(&self, other: &SortEntry) -> bool {
+        self.key.cmp(&other.key) == Ordering::Equal
+    }
+
+    /// Approximate memory taken by the entry
+    pub fn size(&self) -> usize {
+        let key_size = match &self.key {
+            KeyValue::Text(text) => text.capacity(),
+            KeyValue::Number(_) => 0,
+        };
+        std::mem::size_of::<SortEntry>() + self.line.capacity() + key_size
+    }
+}
+
+impl Ord for SortEntry {
+    fn cmp(&self, other: &Self) -> Ordering {
+        let ordering = self
+            .key
+            .cmp(&other.key)
+            .then_with(|| utils::cmp_encoded(&self.line, &other.line));
+        if self.reverse {
+            ordering.reverse()
+        } else {
+            ordering
+        }
+    }
+}
+
+impl PartialOrd for SortEntry {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for SortEntry {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for SortEntry {}
+
+fn key_text<'a>(key: SortKey, parser: &ComboParser, line: &'a str) -> &'a str {
+    let (login, password) = parser
+        .split(line)
+        .map(|combo| (combo.login, combo.password))
+        .unwrap_or((line, ""));
+    match key {
+        SortKey::Login => login,
+        SortKey::Domain => login
+            .split_once('@')
+            .map(|(_, domain)| domain)
+            .unwrap_or(""),
+        SortKey::Password => password,
+        SortKey::Line | SortKey::Length => line,
+    }
+}
+
+/// Number at the start of the text like `sort -n` does, text without it is 0
+fn leading_number(text: &str) -> f64 {
+    let text = text.trim_start();
+    let end = text
+        .char_indices()
+        .find(|&(i, c)| !(c.is_ascii_digit() || c == '.' || (i == 0 && c == '-')))
+        .map(|(i, _)| i)
+        .unwrap_or(text.len());
+
+    let mut number = &text[..end];
+    // `1.2.3` is read as `1.2`
+    if let Some(second_dot) = number.match_indices('.').nth(1).map(|(i, _)| i) {
+        number = &number[..second_dot];
+    }
+    number.parse().unwrap_or(0.0)
+}
@@ -0,0 +1,125 @@
+use std::{
+    cmp::{Ordering, Reverse},
+    collections::BinaryHeap,
+    fs::File,
+    io::{BufRead, BufReader, Lines},
+    path::{Path, PathBuf},
+};
+
+use encoding_rs_io::DecodeReaderBytes;
+
+use super::utils::{self, open_file_r};
+use crate::errors::core_error::CoreError;
+
+/// Lines of a file which have to go in ascending order of their bytes like after `LC_ALL=C sort`,
+/// the order is checked while reading
+pub struct SortedLines {
+    lines: Lines<BufReader<DecodeReaderBytes<File, Vec<u8>>>>,
+    path: PathBuf,
+    line_num: usize,
+    last: Option<String>,
+}
+
+impl SortedLines {
+    pub fn open(path: &Path) -> Result<Self, CoreError> {
+        let file = open_file_r(path)?;
+        Ok(SortedLines {
+            lines: utils::reader_from_file(file).lines(),
+            path: path.to_owned(),
+            line_num: 0,
+            last: None,
+        })
+    }
+
+    pub fn next_line(&mut self) -> Result<Option<String>, CoreError> {
+        for combo in self.lines.by_ref() {
+            self.line_num += 1;
+
+            let combo = match combo {
+                Ok(combo) => combo,
+                Err(err) => {
+                    eprintln!(
+                        "Can't read combo on line {} in file {}. {}",
+                        self.line_num,
+                        self.path.display(),
+                        err
+                    );
+                    continue;
+                }
+            };
+
+            if let Some(last) = &self.last {
+                if utils::cmp_encoded(&combo, last) == Ordering::Less {
+                    return Err(CoreError::UnsortedInput {
+                        path: self.path.clone(),
+                        line: self.line_num,
+                    });
+                }
+            }
+
+            self.last = Some(combo.clone());
+            return Ok(Some(combo));
+        }
+
+        Ok(None)
+    }
+}
+
+/// K-way merge of sorted files, yields lines with the number of the file they came from.
+/// Equal lines are yielded in the order of the files
+pub struct MergedLines {
+    readers: Vec<SortedLines>,
+    heap: BinaryHeap<Reverse<HeapLine>>,
+}
+
+/// Line of the merge ordered by its bytes, then by the file number
+#[derive(Debug, PartialEq, Eq)]
+struct HeapLine {
+    combo: String,
+    file_num: usize,
+}
+
+impl Ord for HeapLine {
+    fn cmp(&self, other: &Self) -> Ordering {
+        utils::cmp_encoded(&self.combo, &other.combo).then(self.file_num.cmp(&other.file_num))
+    }
+}
+
+impl PartialOrd for HeapLine {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl MergedLines {
+    pub fn open(paths: &[PathBuf]) -> Result<Self, CoreError> {
+        let mut readers = Vec::with_capacity(paths.len());
+        let mut heap = BinaryHeap::with_capacity(paths.len());
+
+        for (file_num, path) in paths.iter().enumerate() {
+            let mut reader = SortedLines::open(path)?;
+            if let Some(combo) = reader.next_line()? {
+                heap.push(Reverse(HeapLine { combo, file_num }));
+            }
+            readers.push(reader);
+        }
+
+        Ok(MergedLines { readers, heap })
+    }
+
+    pub fn next_line(&mut self) -> Result<Option<(String, usize)>, CoreError> {
+        let Reverse(HeapLine { combo, file_num }) = match self.heap.pop() {
+            Some(next) => next,
+            None => return Ok(None),
+        };
+
+        if let Some(next) = self.readers[file_num].next_line()? {
+            self.heap.push(Reverse(HeapLine {
+                combo: next,
+                file_num,
+            }));
+        }
+
+        Ok(Some((combo, file_num)))
+    }
+}
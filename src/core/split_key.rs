@@ -0,0 +1,87 @@
+use std::{fmt, str::FromStr};
+
+use clap::{ArgEnum, PossibleValue};
+
+use super::combo::Combo;
+
+/// Bounds of password length buckets, the last one is open
+const PASSWORD_LENGTH_BUCKETS: [usize; 6] = [1, 6, 8, 10, 12, 16];
+
+/// Part of the combo which picks the output file in split-by-key
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ArgEnum)]
+pub enum SplitKey {
+    #[default]
+    Domain,
+    Tld,
+    FirstChar,
+    PasswordLength,
+}
+
+impl SplitKey {
+    /// Key of the combo, `None` when the combo has no such part
+    pub fn extract(self, combo: &Combo) -> Option<String> {
+        let (login, password) = (combo.login, combo.password);
+
+        let domain = || {
+            login
+                .rsplit_once('@')
+                .map(|(_, domain)| domain.to_lowercase())
+        };
+
+        match self {
+            SplitKey::Domain => domain().filter(|domain| !domain.is_empty()),
+            SplitKey::Tld => domain()?
+                .rsplit_once('.')
+                .map(|(_, tld)| tld.to_owned())
+                .filter(|tld| !tld.is_empty()),
+            SplitKey::FirstChar => login.chars().next().map(|c| c.to_lowercase().to_string()),
+            SplitKey::PasswordLength => {
+                let length = password.chars().count();
+                let bucket = PASSWORD_LENGTH_BUCKETS
+                    .iter()
+                    .rposition(|&start| length >= start)?;
+                match PASSWORD_LENGTH_BUCKETS.get(bucket + 1) {
+                    Some(end) => Some(format!(
+                        "len_{}-{}",
+                        PASSWORD_LENGTH_BUCKETS[bucket],
+                        end - 1
+                    )),
+                    None => Some(format!("len_{}+", PASSWORD_LENGTH_BUCKETS[bucket])),
+                }
+            }
+        }
+    }
+
+    pub fn possible_values() -> impl Iterator<Item = PossibleValue<'static>> {
+        SplitKey::value_variants()
+            .iter()
+            .filter_map(ArgEnum::to_possible_value)
+    }
+}
+
+impl fmt::Display for SplitKey {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            SplitKey::Domain => write!(f, "домен"),
+            SplitKey::Tld => write!(f, "доменная зона"),
+            SplitKey::FirstChar => write!(f, "первый символ логина"),
+            SplitKey::PasswordLength => write!(f, "длина пароля"),
+        }
+    }
+}
+
+impl FromStr for SplitKey {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let key = match s {
+            "domain" => SplitKey::Domain,
+            "tld" => SplitKey::Tld,
+            "first-char" => SplitKey::FirstChar,
+            "password-length" => SplitKey::PasswordLength,
+            _ => return Err("Такого ключа нет".to_owned()),
+        };
+
+        Ok(key)
+    }
+}
@@ -0,0 +1,371 @@
+use std::mem;
+
+/// Statements of the SQL dump which are needed to import it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SqlItem {
+    CreateTable {
+        table: String,
+        columns: Vec<String>,
+    },
+    /// Start of the INSERT values, `columns` are the ones listed in the statement
+    Insert {
+        table: String,
+        columns: Option<Vec<String>>,
+    },
+    /// Values of one tuple of the last INSERT, NULL is empty
+    Row(Vec<String>),
+}
+
+/// Keywords which start table definitions that aren't columns
+const CONSTRAINT_KEYWORDS: [&str; 9] = [
+    "PRIMARY",
+    "KEY",
+    "INDEX",
+    "UNIQUE",
+    "CONSTRAINT",
+    "FOREIGN",
+    "FULLTEXT",
+    "SPATIAL",
+    "CHECK",
+];
+const INSERT_MODIFIERS: [&str; 4] = ["LOW_PRIORITY", "DELAYED", "HIGH_PRIORITY", "IGNORE"];
+
+/// Reads `mysqldump`-style dumps line by line. Values of INSERT statements are read tuple by tuple,
+/// so extended inserts are never kept in memory whole
+#[derive(Debug, Default)]
+pub struct SqlScanner {
+    /// Current statement, of INSERT statements only the part before the values
+    statement: String,
+    /// Values of an INSERT are being read
+    values: bool,
+    quote: Option<char>,
+    escaped: bool,
+    /// Depth of the parentheses in the values, tuples are at the depth 1
+    depth: usize,
+    row: Vec<String>,
+    value: String,
+    quoted: bool,
+}
+
+impl SqlScanner {
+    /// Whether the previous statement is finished, comment lines are skipped only there
+    pub fn is_idle(&self) -> bool {
+        !self.values && self.quote.is_none() && self.statement.trim().is_empty()
+    }
+
+    /// Reads the line, found statements and tuples are added to `items`
+    pub fn feed(&mut self, line: &str, items: &mut Vec<SqlItem>) {
+        let mut chars = line.chars().chain(std::iter::once('\n')).peekable();
+
+        while let Some(c) = chars.next() {
+            if self.values {
+                if self.feed_values(c, chars.peek().copied(), items) {
+                    chars.next();
+                }
+                continue;
+            }
+
+            if let Some(quote) = self.quote {
+                self.statement.push(c);
+                if self.escaped {
+                    self.escaped = false;
+                } else if c == '\\' && quote != '`' {
+                    self.escaped = true;
+                } else if c == quote {
+                    self.quote = None;
+                }
+                continue;
+            }
+
+            match c {
+                '\'' | '"' | '`' => {
+                    self.quote = Some(c);
+                    self.statement.push(c);
+                }
+                ';' => {
+                    if let Some(item) = parse_create_table(&self.statement) {
+                        items.push(item);
+                    }
+                    self.statement.clear();
+                }
+                c if (c == '(' || c.is_whitespace()) && self.is_values_start() => {
+                    if let Some(item) = parse_insert(&self.statement) {
+                        items.push(item);
+                    }
+                    self.statement.clear();
+                    self.values = true;
+                    if c == '(' {
+                        self.depth = 1;
+                    }
+                }
+                c => self.statement.push(c),
+            }
+        }
+    }
+
+    /// Returns whether the next character is read too
+    fn feed_values(&mut self, c: char, next: Option<char>, items: &mut Vec<SqlItem>) -> bool {
+        if let Some(quote) = self.quote {
+            if self.escaped {
+                self.escaped = false;
+                if self.depth > 1 {
+                    self.value.push(c);
+                } else {
+                    self.value.push(unescape(c));
+                }
+            } else if c == '\\' {
+                self.escaped = true;
+                if self.depth > 1 {
+                    self.value.push(c);
+                }
+            } else if c == quote {
+                // doubled quotes are a quote in the value
+                if next == Some(quote) {
+                    self.value.push(quote);
+                    return true;
+                }
+                self.quote = None;
+                if self.depth > 1 {
+                    self.value.push(c);
+                }
+            } else {
+                self.value.push(c);
+            }
+            return false;
+        }
+
+        match c {
+            '\'' | '"' if self.depth > 0 => {
+                self.quote = Some(c);
+                if self.depth > 1 {
+                    self.value.push(c);
+                } else {
+                    // introducers like _binary or _utf8mb4 aren't a part of the value
+                    self.value.clear();
+                    self.quoted = true;
+                }
+            }
+            '(' => {
+                self.depth += 1;
+                if self.depth > 1 {
+                    self.value.push(c);
+                }
+            }
+            ')' if self.depth == 1 => {
+                self.finish_value();
+                items.push(SqlItem::Row(mem::take(&mut self.row)));
+                self.depth = 0;
+            }
+            ')' if self.depth > 1 => {
+                self.depth -= 1;
+                self.value.push(c);
+            }
+            ',' if self.depth == 1 => self.finish_value(),
+            ';' if self.depth == 0 => {
+                self.values = false;
+                self.statement.clear();
+            }
+            c if c.is_whitespace() && self.depth <= 1 => (),
+            c if self.depth > 0 => self.value.push(c),
+            _ => (),
+        }
+
+        false
+    }
+
+    fn finish_value(&mut self) {
+        let value = mem::take(&mut self.value);
+        let value = if !self.quoted && value.eq_ignore_ascii_case("NULL") {
+            String::new()
+        } else {
+            value
+        };
+        self.row.push(value);
+        self.quoted = false;
+    }
+
+    /// Statement is an INSERT which ends with the VALUES keyword
+    fn is_values_start(&self) -> bool {
+        let statement = self.statement.trim_end();
+        let start = match statement.len().checked_sub("VALUES".len()) {
+            Some(start) => start,
+            None => return false,
+        };
+        match statement.get(start..) {
+            Some(end) if end.eq_ignore_ascii_case("VALUES") => (),
+            _ => return false,
+        }
+        let separated = match statement[..start].chars().last() {
+            Some(c) => c.is_whitespace() || c == ')' || c == '`',
+            None => false,
+        };
+
+        let body = statement_body(statement);
+        separated
+            && (strip_keyword(body, "INSERT").is_some() || strip_keyword(body, "REPLACE").is_some())
+    }
+}
+
+/// Character of the MySQL escape sequence
+fn unescape(c: char) -> char {
+    match c {
+        '0' => '\0',
+        'b' => '\u{8}',
+        'n' => '\n',
+        'r' => '\r',
+        't' => '\t',
+        'Z' => '\u{1a}',
+        c => c,
+    }
+}
+
+fn parse_insert(statement: &str) -> Option<SqlItem> {
+    let body = statement_body(statement);
+    let mut rest = strip_keyword(body, "INSERT").or_else(|| strip_keyword(body, "REPLACE"))?;
+    for modifier in INSERT_MODIFIERS {
+        rest = strip_keyword(rest, modifier).unwrap_or(rest);
+    }
+    rest = strip_keyword(rest, "INTO").unwrap_or(rest);
+
+    let (table, rest) = split_identifier(rest)?;
+    let columns = parenthesized(rest).map(|columns| {
+        split_top_level(columns)
+            .into_iter()
+            .filter_map(|column| split_identifier(column).map(|(column, _)| column))
+            .collect()
+    });
+
+    Some(SqlItem::Insert { table, columns })
+}
+
+fn parse_create_table(statement: &str) -> Option<SqlItem> {
+    let mut rest = strip_keyword(statement_body(statement), "CREATE")?;
+    rest = strip_keyword(rest, "TEMPORARY").unwrap_or(rest);
+    rest = strip_keyword(rest, "TABLE")?;
+    if let Some(if_rest) = strip_keyword(rest, "IF") {
+        rest = strip_keyword(strip_keyword(if_rest, "NOT")?, "EXISTS")?;
+    }
+
+    let (table, rest) = split_identifier(rest)?;
+    let columns = split_top_level(parenthesized(rest)?)
+        .into_iter()
+        .filter(|definition| {
+            !CONSTRAINT_KEYWORDS
+                .iter()
+                .any(|keyword| strip_keyword(definition, keyword).is_some())
+        })
+        .filter_map(|definition| split_identifier(definition).map(|(column, _)| column))
+        .collect();
+
+    Some(SqlItem::CreateTable { table, columns })
+}
+
+/// Statement without the leading whitespace and comments
+fn statement_body(statement: &str) -> &str {
+    let mut statement = statement.trim_start();
+    while let Some(comment) = statement.strip_prefix("/*") {
+        statement = match comment.find("*/") {
+            Some(end) => comment[end + 2..].trim_start(),
+            None => "",
+        };
+    }
+    statement
+}
+
+/// Text after the keyword when the text starts with it
+fn strip_keyword<'a>(text: &'a str, keyword: &str) -> Option<&'a str> {
+    let start = text.get(..keyword.len())?;
+    if !start.eq_ignore_ascii_case(keyword) {
+        return None;
+    }
+    let rest = &text[keyword.len()..];
+    match rest.chars().next() {
+        Some(c) if c.is_alphanumeric() || c == '_' => None,
+        _ => Some(rest.trim_start()),
+    }
+}
+
+/// Name and the text after it, names may be quoted and have the database like `db`.`users`
+fn split_identifier(text: &str) -> Option<(String, &str)> {
+    let mut rest = text.trim_start();
+    loop {
+        let (name, after) = match rest.chars().next()? {
+            quote @ ('`' | '"' | '[') => {
+                let close = if quote == '[' { ']' } else { quote };
+                let end = rest[1..].find(close)? + 1;
+                (rest[1..end].to_owned(), &rest[end + 1..])
+            }
+            _ => {
+                let end = rest
+                    .find(|c: char| !(c.is_alphanumeric() || c == '_' || c == '$'))
+                    .unwrap_or(rest.len());
+                if end == 0 {
+                    return None;
+                }
+                (rest[..end].to_owned(), &rest[end..])
+            }
+        };
+
+        match after.strip_prefix('.') {
+            Some(after) => rest = after,
+            None => return Some((name, after.trim_start())),
+        }
+    }
+}
+
+/// Text inside the parentheses the text starts with
+fn parenthesized(text: &str) -> Option<&str> {
+    let inside = text.trim_start().strip_prefix('(')?;
+    let mut depth = 0usize;
+    let mut quote: Option<char> = None;
+    let mut escaped = false;
+
+    for (i, c) in inside.char_indices() {
+        match quote {
+            Some(_) if escaped => escaped = false,
+            Some(q) if c == '\\' && q != '`' => escaped = true,
+            Some(q) if c == q => quote = None,
+            Some(_) => (),
+            None => match c {
+                '\'' | '"' | '`' => quote = Some(c),
+                '(' => depth += 1,
+                ')' if depth == 0 => return Some(&inside[..i]),
+                ')' => depth -= 1,
+                _ => (),
+            },
+        }
+    }
+
+    None
+}
+
+/// Parts of the list separated by commas which aren't quoted or in parentheses
+fn split_top_level(list: &str) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let mut depth = 0usize;
+    let mut quote: Option<char> = None;
+    let mut escaped = false;
+    let mut start = 0usize;
+
+    for (i, c) in list.char_indices() {
+        match quote {
+            Some(_) if escaped => escaped = false,
+            Some(q) if c == '\\' && q != '`' => escaped = true,
+            Some(q) if c == q => quote = None,
+            Some(_) => (),
+            None => match c {
+                '\'' | '"' | '`' => quote = Some(c),
+                '(' => depth += 1,
+                ')' => depth = depth.saturating_sub(1),
+                ',' if depth == 0 => {
+                    parts.push(list[start..i].trim());
+                    start = i + 1;
+                }
+                _ => (),
+            },
+        }
+    }
+    parts.push(list[start..].trim());
+
+    parts
+}
@@ -0,0 +1,216 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fmt,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+use clap::{ArgEnum, PossibleValue};
+use glob::{MatchOptions, Pattern};
+use walkdir::WalkDir;
+
+// file names are case-insensitive on Windows, so are the patterns
+const MATCH_OPTIONS: MatchOptions = MatchOptions {
+    case_sensitive: false,
+    require_literal_separator: true,
+    require_literal_leading_dot: false,
+};
+
+/// What is done with symbolic links found in target directories
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ArgEnum)]
+pub enum SymlinkPolicy {
+    Skip,
+    #[default]
+    Files,
+    Follow,
+}
+
+impl SymlinkPolicy {
+    pub fn possible_values() -> impl Iterator<Item = PossibleValue<'static>> {
+        SymlinkPolicy::value_variants()
+            .iter()
+            .filter_map(ArgEnum::to_possible_value)
+    }
+}
+
+impl fmt::Display for SymlinkPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            SymlinkPolicy::Skip => write!(f, "пропускать"),
+            SymlinkPolicy::Files => write!(f, "только файлы"),
+            SymlinkPolicy::Follow => write!(f, "переходить"),
+        }
+    }
+}
+
+impl FromStr for SymlinkPolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let policy = match s {
+            "skip" => SymlinkPolicy::Skip,
+            "files" => SymlinkPolicy::Files,
+            "follow" => SymlinkPolicy::Follow,
+            _ => return Err("Такого варианта нет".to_owned()),
+        };
+
+        Ok(policy)
+    }
+}
+
+/// Files listed for `--target` values
+#[derive(Debug, Default)]
+pub struct ExpandedTargets {
+    pub files: Vec<PathBuf>,
+    /// Directory of the file relative to the one it was found in, starting with that directory's name
+    pub rel_dirs: HashMap<PathBuf, PathBuf>,
+    seen: HashSet<PathBuf>,
+}
+
+impl ExpandedTargets {
+    fn push(&mut self, path: PathBuf, rel_dir: Option<PathBuf>) {
+        if !self.seen.insert(path.clone()) {
+            return;
+        }
+
+        if let Some(rel_dir) = rel_dir {
+            self.rel_dirs.insert(path.clone(), rel_dir);
+        }
+        self.files.push(path);
+    }
+}
+
+/// Picks files from target directories and glob patterns, files given explicitly are always taken
+#[derive(Debug, Default)]
+pub struct TargetFilter {
+    pub include: Vec<Pattern>,
+    pub exclude: Vec<Pattern>,
+    pub depth: Option<usize>,
+    pub symlinks: SymlinkPolicy,
+}
+
+impl TargetFilter {
+    pub fn expand<'a>(&self, targets: impl Iterator<Item = &'a str>) -> ExpandedTargets {
+        let mut expanded = ExpandedTargets::default();
+
+        for target in targets {
+            let path = Path::new(target);
+            if path.is_dir() {
+                self.expand_dir(path, None, None, &mut expanded);
+            } else if !path.exists() && is_glob(target) {
+                self.expand_glob(target, &mut expanded);
+            } else {
+                expanded.push(path.to_owned(), None);
+            }
+        }
+
+        expanded
+    }
+
+    fn expand_glob(&self, target: &str, expanded: &mut ExpandedTargets) {
+        // the walk starts from the last directory before the first wildcard
+        let mut root = PathBuf::new();
+        let mut pattern_depth = 0usize;
+        for component in Path::new(target).components() {
+            if pattern_depth > 0 || is_glob(&component.as_os_str().to_string_lossy()) {
+                pattern_depth += 1;
+            } else {
+                root.push(component);
+            }
+        }
+
+        // `**` matches any number of directories
+        let max_depth = if target.contains("**") {
+            None
+        } else {
+            Some(pattern_depth)
+        };
+
+        // paths of the walk start with the root, so does the pattern
+        let pattern = if root.as_os_str().is_empty() {
+            root.push(".");
+            Pattern::new(&format!("./{}", target))
+        } else {
+            Pattern::new(target)
+        };
+
+        match pattern {
+            Ok(pattern) => self.expand_dir(&root, Some(&pattern), max_depth, expanded),
+            Err(err) => eprintln!("Wrong pattern {}. {}", target, err),
+        }
+    }
+
+    fn expand_dir(
+        &self,
+        root: &Path,
+        pattern: Option<&Pattern>,
+        max_depth: Option<usize>,
+        expanded: &mut ExpandedTargets,
+    ) {
+        let mut walker = WalkDir::new(root)
+            .follow_links(self.symlinks == SymlinkPolicy::Follow)
+            .sort_by_file_name();
+        match (self.depth, max_depth) {
+            (Some(depth), Some(max_depth)) => walker = walker.max_depth(depth.min(max_depth)),
+            (Some(depth), None) | (None, Some(depth)) => walker = walker.max_depth(depth),
+            (None, None) => (),
+        }
+
+        for entry in walker {
+            let entry = match entry {
+                Ok(entry) => entry,
+                Err(err) => {
+                    eprintln!("Can't read file {}. {}", root.display(), err);
+                    continue;
+                }
+            };
+
+            if entry.path_is_symlink() && self.symlinks == SymlinkPolicy::Skip {
+                continue;
+            }
+
+            let path = entry.path();
+            if !path.is_file() {
+                continue;
+            }
+
+            if let Some(pattern) = pattern {
+                if !pattern.matches_path_with(path, MATCH_OPTIONS) {
+                    continue;
+                }
+            }
+
+            let rel_path = path.strip_prefix(root).unwrap_or(path);
+
+            if !self.accepts(path, rel_path) {
+                continue;
+            }
+
+            let mut rel_dir = PathBuf::from(root.file_name().unwrap_or_default());
+            rel_dir.push(rel_path.parent().unwrap_or_else(|| Path::new("")));
+            expanded.push(path.to_owned(), Some(rel_dir));
+        }
+    }
+
+    /// Patterns with a path separator are matched against the path inside of the directory,
+    /// others against the file name
+    fn accepts(&self, path: &Path, rel_path: &Path) -> bool {
+        let matches = |pattern: &Pattern| {
+            if pattern.as_str().contains(['/', '\\']) {
+                pattern.matches_path_with(rel_path, MATCH_OPTIONS)
+            } else {
+                match path.file_name().and_then(|name| name.to_str()) {
+                    Some(name) => pattern.matches_with(name, MATCH_OPTIONS),
+                    None => false,
+                }
+            }
+        };
+
+        (self.include.is_empty() || self.include.iter().any(matches))
+            && !self.exclude.iter().any(matches)
+    }
+}
+
+fn is_glob(target: &str) -> bool {
+    target.contains(['*', '?', '['])
+}
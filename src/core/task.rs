@@ -10,12 +10,20 @@ pub enum Task {
     ExtractDuplicates,
     SplitByLines,
     SplitByParts,
+    SplitBySize,
+    SplitByKey,
+    Shard,
     Merge,
     Shuffle,
+    Sample,
+    Slice,
     ExtractLogins,
     ExtractPasswords,
     ExtractPhones,
+    ExtractCombos,
     Compare,
+    BuildIndex,
+    Sort,
 }
 
 impl Task {
@@ -24,17 +32,33 @@ impl Task {
         match self {
             Task::RemoveDomains => "_no_domains",
             Task::RemoveDuplicatesFast | Task::RemoveDuplicatesSlow => "_no_duplicates",
-            Task::SplitByLines | Task::SplitByParts => "_splitted_{num}",
+            Task::SplitByLines | Task::SplitByParts | Task::SplitBySize => "_splitted_{num}",
+            Task::SplitByKey => "_{key}",
+            Task::Shard => "_shard_{num}",
             Task::Merge => "_merged",
             Task::Shuffle => "_randomized",
+            Task::Sample => "_sample",
+            Task::Slice => "_slice",
             Task::ExtractLogins => "_logins",
             Task::ExtractPasswords => "_passwords",
             Task::ExtractPhones => "_phones",
+            Task::ExtractCombos => "_extracted",
             Task::ExtractDuplicates => "_duplicates",
-            Task::Compare => "_uniq_{file}",
+            Task::Compare => "_{op}_{file}",
+            Task::BuildIndex => "_index",
+            Task::Sort => "_sorted",
         }
     }
 
+    /// Tasks whose results are combos and can be written in `--output-format`.
+    /// Logins, passwords and the index have no combos to format
+    pub fn formats_output(self) -> bool {
+        !matches!(
+            self,
+            Task::ExtractLogins | Task::ExtractPasswords | Task::BuildIndex
+        )
+    }
+
     pub fn possible_values() -> impl Iterator<Item = PossibleValue<'static>> {
         Task::value_variants()
             .iter()
@@ -51,13 +75,21 @@ impl fmt::Display for Task {
             }
             Task::SplitByLines => write!(f, "Разделение по количеству строк"),
             Task::SplitByParts => write!(f, "Разделение по частям"),
+            Task::SplitBySize => write!(f, "Разделение по размеру"),
+            Task::SplitByKey => write!(f, "Разделение по ключу"),
+            Task::Shard => write!(f, "Шардирование"),
             Task::Merge => write!(f, "Склеивание"),
             Task::Shuffle => write!(f, "Перемешивание"),
+            Task::Sample => write!(f, "Случайная выборка"),
+            Task::Slice => write!(f, "Вырезка строк"),
             Task::ExtractLogins => write!(f, "Получение логинов"),
             Task::ExtractPasswords => write!(f, "Получение паролей"),
             Task::ExtractPhones => write!(f, "Нормализация телефонов"),
+            Task::ExtractCombos => write!(f, "Извлечение комбо из текста"),
             Task::ExtractDuplicates => write!(f, "Дубликаты"),
             Task::Compare => write!(f, "Сравнение"),
+            Task::BuildIndex => write!(f, "Построение индекса"),
+            Task::Sort => write!(f, "Сортировка"),
         }
     }
 }
@@ -72,13 +104,21 @@ impl FromStr for Task {
             "remove-duplicates-slow" => Task::RemoveDuplicatesSlow,
             "split-by-lines" => Task::SplitByLines,
             "split-by-parts" => Task::SplitByParts,
+            "split-by-size" => Task::SplitBySize,
+            "split-by-key" => Task::SplitByKey,
+            "shard" => Task::Shard,
             "merge" => Task::Merge,
             "shuffle" => Task::Shuffle,
+            "sample" => Task::Sample,
+            "slice" => Task::Slice,
             "extract-logins" => Task::ExtractLogins,
             "extract-passwords" => Task::ExtractPasswords,
             "extract-phones" => Task::ExtractPhones,
+            "extract-combos" => Task::ExtractCombos,
             "extract-duplicates" => Task::ExtractDuplicates,
             "compare" => Task::Compare,
+            "build-index" => Task::BuildIndex,
+            "sort" => Task::Sort,
             _ => return Err("Такого пока нет".to_owned()),
         };
 
@@ -1,11 +1,12 @@
-use std::borrow::BorrowMut;
-use std::collections::HashSet;
+use std::borrow::{BorrowMut, Cow};
+use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet};
 use std::hash::BuildHasherDefault;
 use std::io;
 use std::io::Read;
 use std::io::Write;
 use std::{
-    fs::{File, OpenOptions},
+    fs::{self, File, OpenOptions},
     io::{BufRead, BufReader},
     path::{Path, PathBuf},
 };
@@ -15,17 +16,32 @@ use encoding_rs_io::{DecodeReaderBytes, DecodeReaderBytesBuilder};
 use nohash_hasher::NoHashHasher;
 use walkdir::WalkDir;
 
+use super::combo::ComboParser;
+
 pub type NoHashSet = HashSet<u64, BuildHasherDefault<NoHashHasher<u64>>>;
+pub type NoHashMap<V> = HashMap<u64, V, BuildHasherDefault<NoHashHasher<u64>>>;
 
-pub fn save_results<T>(results: &mut Vec<T>, file: &Option<File>) -> io::Result<()>
+/// Combos of the results are written in the output format of `output`. Temporary files
+/// and lines which are in the output format already are written with `None`
+pub fn save_results<T>(
+    results: &mut Vec<T>,
+    file: &Option<File>,
+    output: Option<&ComboParser>,
+) -> io::Result<()>
 where
     T: AsRef<str>,
 {
-    if file.is_none() {
+    if file.is_none() || results.is_empty() {
         return Ok(());
     }
     let mut file = file.as_ref().unwrap();
-    let results_str: Vec<&str> = results.iter().map(|v| v.as_ref()).collect();
+    let results_str: Vec<Cow<str>> = results
+        .iter()
+        .map(|v| match output {
+            Some(output) => output.format_line(v.as_ref()),
+            None => Cow::Borrowed(v.as_ref()),
+        })
+        .collect();
     let mut results_str = results_str.join("\n");
     results_str.push('\n');
     let encoded = WINDOWS_1252.encode(results_str.as_str());
@@ -34,6 +50,14 @@ where
     Ok(())
 }
 
+/// Compares lines by their bytes in the files, it's the order of `LC_ALL=C sort`
+pub fn cmp_encoded(a: &str, b: &str) -> Ordering {
+    if a.is_ascii() && b.is_ascii() {
+        return a.cmp(b);
+    }
+    WINDOWS_1252.encode(a).0.cmp(&WINDOWS_1252.encode(b).0)
+}
+
 pub fn open_results_file<P: AsRef<Path>>(path: P) -> io::Result<File> {
     let path = path.as_ref();
 
@@ -50,6 +74,11 @@ pub fn open_results_file<P: AsRef<Path>>(path: P) -> io::Result<File> {
         .open(path)
 }
 
+/// Opens a results file created earlier by `open_results_file` to continue writing
+pub fn reopen_results_file<P: AsRef<Path>>(path: P) -> io::Result<File> {
+    OpenOptions::new().append(true).open(path)
+}
+
 pub fn count_lines(file: File) -> usize {
     let buffer = BufReader::new(file);
 
@@ -75,6 +104,16 @@ pub fn reader_from_file(file: File) -> BufReader<DecodeReaderBytes<File, Vec<u8>
     )
 }
 
+/// Line of the mapped file decoded like `reader_from_file` lines, without the `\n` or `\r\n` ending
+pub fn decode_line(line: &[u8]) -> String {
+    let line = line.strip_suffix(b"\n").unwrap_or(line);
+    let line = line.strip_suffix(b"\r").unwrap_or(line);
+    WINDOWS_1252
+        .decode_without_bom_handling(line)
+        .0
+        .into_owned()
+}
+
 pub fn read_lines<'a>(path: &Path, buffer: &'a mut String) -> io::Result<Vec<&'a str>> {
     let file = open_file_r(path)?;
     let mut reader = reader_from_file(file);
@@ -85,6 +124,17 @@ pub fn read_lines<'a>(path: &Path, buffer: &'a mut String) -> io::Result<Vec<&'a
     Ok(lines)
 }
 
+/// Lines of the file decoded by `decode_line`, a newline at the end doesn't start a line
+pub fn read_decoded_lines(path: &Path) -> io::Result<Vec<String>> {
+    let data = fs::read(path)?;
+    if data.is_empty() {
+        return Ok(Vec::new());
+    }
+    let data = data.strip_suffix(b"\n").unwrap_or(&data);
+
+    Ok(data.split(|&char| char == b'\n').map(decode_line).collect())
+}
+
 pub fn open_file_r(path: &Path) -> io::Result<File> {
     let file = OpenOptions::new().read(true).open(path)?;
     Ok(file)
@@ -94,23 +144,24 @@ pub fn open_file_r(path: &Path) -> io::Result<File> {
 pub fn save_results_hashset<'a>(
     results: impl Iterator<Item = &'a str>,
     file: &mut File,
+    output: &ComboParser,
 ) -> io::Result<()> {
-    let results_str = join(results, "\n");
+    let results_str = join(results.map(|line| output.format_line(line)), "\n");
     let encoded = WINDOWS_1252.encode(&results_str);
     file.write_all(&encoded.0)?;
     Ok(())
 }
 
-fn join<'a>(mut iter: impl Iterator<Item = &'a str>, joiner: &str) -> String {
+fn join<T: AsRef<str>>(mut iter: impl Iterator<Item = T>, joiner: &str) -> String {
     let mut joined = String::new();
 
     if let Some(item) = iter.next() {
-        joined.push_str(item);
+        joined.push_str(item.as_ref());
     }
 
     for item in iter {
         joined.push_str(joiner);
-        joined.push_str(item);
+        joined.push_str(item.as_ref());
     }
 
     joined
@@ -151,6 +202,21 @@ pub fn ask_for_number(input: &str) -> usize {
     }
 }
 
+/// Parses sizes like `1048576`, `512KB`, `100MB` or `2GB`, units are binary
+pub fn parse_size(size: &str) -> Option<usize> {
+    let size = size.trim().to_uppercase();
+    let size = size.strip_suffix('B').unwrap_or(&size);
+
+    let (number, multiplier) = match size.chars().last()? {
+        'K' => (&size[..size.len() - 1], 1024),
+        'M' => (&size[..size.len() - 1], 1024 * 1024),
+        'G' => (&size[..size.len() - 1], 1024 * 1024 * 1024),
+        _ => (size, 1),
+    };
+
+    number.trim().parse::<usize>().ok()?.checked_mul(multiplier)
+}
+
 pub fn list_dir(path: PathBuf) -> io::Result<Vec<PathBuf>> {
     let mut listed_files: Vec<PathBuf> = Vec::new();
     if path.is_dir() {
@@ -173,3 +239,27 @@ pub fn list_dir(path: PathBuf) -> io::Result<Vec<PathBuf>> {
 
     Ok(listed_files)
 }
+
+/// Empty directory for the files of the test, it's removed by the test
+#[cfg(test)]
+pub fn test_dir(name: &str) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("combosutils_{}_{}", name, std::process::id()));
+    if path.exists() {
+        fs::remove_dir_all(&path).unwrap();
+    }
+    fs::create_dir_all(&path).unwrap();
+    path
+}
+
+/// Files written into the results directory and its subdirectories by the test,
+/// sorted by their paths
+#[cfg(test)]
+pub fn test_results(results_path: &Path) -> Vec<PathBuf> {
+    let mut results: Vec<PathBuf> = WalkDir::new(results_path)
+        .into_iter()
+        .map(|entry| entry.unwrap().into_path())
+        .filter(|path| path.is_file())
+        .collect();
+    results.sort();
+    results
+}
@@ -1,7 +1,9 @@
-use std::io;
+use std::{io, path::PathBuf};
 
 use thiserror::Error;
 
+use crate::core::set_op::SetOp;
+
 #[derive(Error, Debug)]
 pub enum CoreError {
     #[error("no results")]
@@ -10,6 +12,21 @@ pub enum CoreError {
     #[error("unexpected error with args")]
     UnexpectedArgs,
 
+    #[error("{0:?} operation can't be used with a hash index")]
+    IndexUnsupportedOp(SetOp),
+
+    #[error("{0:?} operation can't be used with a probabilistic filter")]
+    FilterUnsupportedOp(SetOp),
+
+    #[error("file {} is not sorted, line {line} goes before the previous one", path.display())]
+    UnsortedInput { path: PathBuf, line: usize },
+
+    #[error("results of {} and {} would have the same names", first.display(), second.display())]
+    ResultsCollision { first: PathBuf, second: PathBuf },
+
+    #[error("can't import {}: {reason}", path.display())]
+    Import { path: PathBuf, reason: String },
+
     #[error(transparent)]
     IoError(#[from] io::Error),
     /*#[error("the data for key `{0}` is not available")]
@@ -1,5 +1,4 @@
 #![feature(let_chains)]
-#![feature(hash_drain_filter)]
 
 mod cmd;
 mod core;
@@ -0,0 +1,258 @@
+use std::{
+    cmp::Reverse,
+    collections::BinaryHeap,
+    fs::{self, File},
+    io::{self, BufRead, BufReader, BufWriter, Read, Write},
+    mem,
+    path::{Path, PathBuf},
+    sync::Mutex,
+    time,
+};
+
+use rayon::prelude::*;
+
+use crate::{
+    cmd::Args,
+    core::{
+        hash_index::HashIndex,
+        lines_processor::LinesProcessor,
+        utils::{self, open_file_r},
+    },
+    errors::core_error::CoreError,
+};
+
+const MEGABYTE: usize = 1024 * 1024;
+const HASH_SIZE: usize = mem::size_of::<u64>();
+
+/// Hashes are sorted in runs which fit into the memory budget, the runs are written to disk
+/// and merged into the index, so the targets may be much bigger than the memory
+pub struct IndexBuilder {
+    targets: Vec<PathBuf>,
+    index_path: PathBuf,
+    save_period: usize,
+    memory_budget: usize,
+}
+
+impl LinesProcessor for IndexBuilder {
+    fn new(args: Args, _: PathBuf, save_period: usize) -> Self {
+        let index_path = args.index.expect("--index is required for build-index");
+
+        let mut targets = Vec::new();
+        for target in args.targets {
+            match utils::list_dir(target) {
+                Ok(files) => targets.extend(files),
+                Err(error) => {
+                    panic!("Can't list directory files: {}", error)
+                }
+            }
+        }
+
+        IndexBuilder {
+            targets,
+            index_path,
+            save_period,
+            memory_budget: args.memory * MEGABYTE,
+        }
+    }
+
+    fn process_line(&self, _: &str) -> Option<String> {
+        unreachable!()
+    }
+
+    fn process(self) -> Result<(), CoreError> {
+        println!("Обработка {} файлов", self.targets.len());
+
+        let now = time::Instant::now();
+
+        let runs_path = self.index_path.with_extension("runs");
+        // only one run is in memory, it's sorted and written while the other files wait for it
+        let run_len = (self.memory_budget / HASH_SIZE).max(1);
+
+        let runs = Mutex::new(Runs {
+            run: Vec::with_capacity(run_len),
+            paths: Vec::new(),
+        });
+
+        self.targets
+            .par_iter()
+            .enumerate()
+            .try_for_each(|(file_num, path)| -> io::Result<()> {
+                let file = match open_file_r(path) {
+                    Ok(file) => file,
+                    Err(err) => {
+                        eprintln!("Can't read input file {}. {}", path.display(), err);
+                        return Ok(());
+                    }
+                };
+
+                println!(
+                    "[{}/{}]Файл: {}",
+                    file_num + 1,
+                    self.targets.len(),
+                    path.display()
+                );
+
+                let reader = utils::reader_from_file(file);
+                let mut hashes: Vec<u64> = Vec::with_capacity(self.save_period);
+
+                for (i, combo) in reader.lines().enumerate() {
+                    match combo {
+                        Ok(combo) => hashes.push(seahash::hash(combo.as_bytes())),
+                        Err(err) => {
+                            eprintln!(
+                                "Can't read combo on line {} in file {}. {}",
+                                i,
+                                path.display(),
+                                err
+                            );
+                            continue;
+                        }
+                    }
+
+                    if hashes.len() == self.save_period {
+                        runs.lock()
+                            .unwrap()
+                            .add_hashes(&mut hashes, run_len, &runs_path)?;
+                    }
+                }
+                runs.lock()
+                    .unwrap()
+                    .add_hashes(&mut hashes, run_len, &runs_path)
+            })?;
+
+        let mut runs = runs.into_inner().unwrap();
+        if !runs.run.is_empty() {
+            runs.save_run(&runs_path)?;
+        }
+        drop(runs.run);
+
+        println!("Слияние {} отсортированных частей...", runs.paths.len());
+        println!("Запись индекса {}...", self.index_path.display());
+
+        let old_len = if self.index_path.exists() {
+            HashIndex::open(&self.index_path)?.len()
+        } else {
+            0
+        };
+        let index_len = HashIndex::extend(&self.index_path, MergedRuns::open(&runs.paths)?)?;
+        // hashes which are in the index already aren't written again
+        let new_count = index_len - old_len;
+
+        if runs_path.exists() {
+            if let Err(err) = fs::remove_dir_all(&runs_path) {
+                eprintln!("Can't remove directory {}. {}", runs_path.display(), err);
+            }
+        }
+
+        println!(
+            "Новых хэшей: {}. Всего в индексе: {}. Потрачено: {:?}",
+            new_count,
+            index_len,
+            now.elapsed()
+        );
+
+        Ok(())
+    }
+}
+
+/// Run which is filled and the runs written to disk
+struct Runs {
+    run: Vec<u64>,
+    paths: Vec<PathBuf>,
+}
+
+impl Runs {
+    /// Moves the hashes into the run, the run is written to disk first when they don't fit into it
+    fn add_hashes(
+        &mut self,
+        hashes: &mut Vec<u64>,
+        run_len: usize,
+        runs_path: &Path,
+    ) -> io::Result<()> {
+        if !self.run.is_empty() && self.run.len() + hashes.len() > run_len {
+            self.save_run(runs_path)?;
+        }
+        self.run.append(hashes);
+
+        Ok(())
+    }
+
+    /// Sorts the run and writes it without duplicates as little-endian `u64`s.
+    /// The sort isn't parallel, rayon threads waiting for the lock of the runs would take its jobs
+    fn save_run(&mut self, runs_path: &Path) -> io::Result<()> {
+        self.run.sort_unstable();
+        self.run.dedup();
+
+        fs::create_dir_all(runs_path)?;
+        let run_path = runs_path.join(format!("{}.run", self.paths.len()));
+        let mut writer = BufWriter::new(File::create(&run_path)?);
+        for hash in self.run.iter() {
+            writer.write_all(&hash.to_le_bytes())?;
+        }
+        writer.flush()?;
+        self.run.clear();
+        self.paths.push(run_path);
+
+        Ok(())
+    }
+}
+
+/// Sorted unique hashes of all runs
+struct MergedRuns {
+    readers: Vec<BufReader<File>>,
+    heap: BinaryHeap<Reverse<(u64, usize)>>,
+    last: Option<u64>,
+}
+
+impl MergedRuns {
+    fn open(runs: &[PathBuf]) -> io::Result<Self> {
+        let mut readers = Vec::with_capacity(runs.len());
+        let mut heap = BinaryHeap::with_capacity(runs.len());
+
+        for (run_num, run) in runs.iter().enumerate() {
+            let mut reader = BufReader::new(open_file_r(run)?);
+            if let Some(hash) = read_hash(&mut reader)? {
+                heap.push(Reverse((hash, run_num)));
+            }
+            readers.push(reader);
+        }
+
+        Ok(MergedRuns {
+            readers,
+            heap,
+            last: None,
+        })
+    }
+}
+
+impl Iterator for MergedRuns {
+    type Item = io::Result<u64>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while let Some(Reverse((hash, run_num))) = self.heap.pop() {
+            match read_hash(&mut self.readers[run_num]) {
+                Ok(Some(next)) => self.heap.push(Reverse((next, run_num))),
+                Ok(None) => (),
+                Err(err) => return Some(Err(err)),
+            }
+
+            // runs have no duplicates inside, but the same hash may be in several runs
+            if self.last == Some(hash) {
+                continue;
+            }
+            self.last = Some(hash);
+            return Some(Ok(hash));
+        }
+
+        None
+    }
+}
+
+fn read_hash(reader: &mut impl Read) -> io::Result<Option<u64>> {
+    let mut bytes = [0u8; HASH_SIZE];
+    match reader.read_exact(&mut bytes) {
+        Ok(()) => Ok(Some(u64::from_le_bytes(bytes))),
+        Err(err) if err.kind() == io::ErrorKind::UnexpectedEof => Ok(None),
+        Err(err) => Err(err),
+    }
+}
@@ -1,7 +1,9 @@
 use std::{
+    cmp::Reverse,
     ffi::OsString,
+    fs::{self, File},
     io::BufRead,
-    path::PathBuf,
+    path::{Path, PathBuf},
     sync::{
         atomic::{AtomicUsize, Ordering},
         Mutex,
@@ -14,7 +16,14 @@ use rayon::prelude::*;
 use crate::{
     cmd::Args,
     core::{
+        bloom_filter::BloomFilter,
+        combo::ComboParser,
+        hash_index::HashIndex,
         lines_processor::LinesProcessor,
+        results_layout::ResultsLayout,
+        set_op::{Emit, SetOp},
+        sharded_set::{ShardedHashMap, ShardedHashSet},
+        sorted_lines::{MergedLines, SortedLines},
         task::Task,
         utils::{self, open_file_r, NoHashSet},
     },
@@ -25,9 +34,35 @@ pub struct Comparer {
     targets: Vec<PathBuf>,
     results_path: PathBuf,
     compare_with: Vec<PathBuf>,
+    compare_index: Option<PathBuf>,
     compare_name: OsString,
     save_period: usize,
     task: Task,
+    layout: ResultsLayout,
+    parser: ComboParser,
+    op: SetOp,
+    fp_rate: Option<f64>,
+    exact: bool,
+    emit: Option<Emit>,
+    attribution: bool,
+    sorted: bool,
+}
+
+/// Line of the sorted merge and the side it was found on
+enum Merged {
+    Target(String),
+    /// Line with the number of the first compare file which contains it
+    Both(String, usize),
+    Compare(String),
+}
+
+/// Output of one set operation in the sorted mode
+struct SortedOutput {
+    op: SetOp,
+    file: Option<File>,
+    results: Vec<String>,
+    last: Option<String>,
+    saved: usize,
 }
 
 impl LinesProcessor for Comparer {
@@ -40,10 +75,14 @@ impl LinesProcessor for Comparer {
 
         let compare_name = compare_with.file_stem().unwrap_or_default().to_owned();
 
-        let compare_with_dir = match utils::list_dir(compare_with) {
-            Ok(dir) => dir,
-            Err(error) => {
-                panic!("Can't list directory files: {}", error)
+        let (compare_with_dir, compare_index) = if HashIndex::is_index(&compare_with) {
+            (Vec::new(), Some(compare_with))
+        } else {
+            match utils::list_dir(compare_with) {
+                Ok(dir) => (dir, None),
+                Err(error) => {
+                    panic!("Can't list directory files: {}", error)
+                }
             }
         };
 
@@ -51,21 +90,57 @@ impl LinesProcessor for Comparer {
             targets: args.targets,
             results_path,
             compare_with: compare_with_dir,
+            compare_index,
             compare_name,
             save_period,
             task: args.task,
+            layout: args.layout,
+            parser: args.combo,
+            op: args.op,
+            fp_rate: args.fp_rate,
+            exact: args.exact,
+            emit: args.emit,
+            attribution: args.attribution,
+            sorted: args.sorted,
         }
     }
 
-    fn process_line(_: &str) -> Option<String> {
+    fn process_line(&self, _: &str) -> Option<String> {
         unreachable!()
     }
 
     fn process(self) -> Result<(), CoreError> {
-        println!("Обработка {} файлов", self.targets.len());
+        println!(
+            "Обработка {} файлов. Операция: {}",
+            self.targets.len(),
+            self.op
+        );
 
         let now = time::Instant::now();
 
+        // hashes of the compare side can't be turned back into lines
+        let compare_index = match &self.compare_index {
+            Some(_) if matches!(self.op, SetOp::Symdiff | SetOp::Union) => {
+                return Err(CoreError::IndexUnsupportedOp(self.op))
+            }
+            Some(index_path) => Some(HashIndex::open(index_path)?),
+            None => None,
+        };
+
+        if let Some(fp_rate) = self.fp_rate {
+            if compare_index.is_none() {
+                return self.process_filtered(fp_rate);
+            }
+            println!("Индекс точный, фильтр не используется");
+        }
+
+        if self.sorted {
+            if compare_index.is_none() {
+                return self.process_sorted();
+            }
+            println!("Индекс не сортированный файл, сравнение по хэшам");
+        }
+
         for (file_num, path) in self.targets.iter().enumerate() {
             let inner_now = time::Instant::now();
 
@@ -83,7 +158,7 @@ impl LinesProcessor for Comparer {
 
             println!("Сохранение хэшей строк основного файла...");
 
-            let main_lines: Mutex<NoHashSet> = Mutex::new(NoHashSet::default());
+            let main_lines = ShardedHashSet::new();
             let lines_count = AtomicUsize::new(0);
 
             reader
@@ -106,15 +181,26 @@ impl LinesProcessor for Comparer {
                     };
 
                     let hash = seahash::hash(combo.as_bytes());
-                    main_lines.lock().unwrap().insert(hash);
+                    main_lines.insert(hash);
                     lines_count.fetch_add(1, Ordering::Relaxed);
                 });
 
             let lines_count = lines_count.load(Ordering::Relaxed);
 
             let total_lines = AtomicUsize::new(0);
+            // hashes of the main file which were found in the compare files
+            let matched_lines = ShardedHashMap::new();
 
             println!("Сравнение...");
+            if let Some(index) = &compare_index {
+                println!("Сравнение с индексом: {} хэшей", index.len());
+
+                for hash in main_lines.take_matching(|x| index.contains(x)) {
+                    matched_lines.insert_min(hash, 0);
+                }
+                total_lines.store(index.len(), Ordering::Relaxed);
+            }
+
             self.compare_with
                 .iter()
                 .enumerate()
@@ -131,32 +217,6 @@ impl LinesProcessor for Comparer {
 
                     let reader = utils::reader_from_file(file);
 
-                    let lines: NoHashSet = reader
-                        .lines()
-                        .enumerate()
-                        .filter_map(|(line_num, combo)| {
-                            let combo = match combo {
-                                Ok(combo) => combo,
-                                Err(err) => {
-                                    eprintln!(
-                                        "Can't read combo on line {} in file {}. {}",
-                                        line_num,
-                                        compare_path.display(),
-                                        err
-                                    );
-                                    return None;
-                                }
-                            };
-
-                            total_lines.fetch_add(1, Ordering::Relaxed);
-
-                            let hash = seahash::hash(combo.as_bytes());
-                            Some(hash)
-                        })
-                        .collect();
-
-                    let mut mx = main_lines.lock().unwrap();
-
                     println!(
                         "[{}/{}]Сравнение с: {:?}",
                         file_num + 1,
@@ -164,60 +224,67 @@ impl LinesProcessor for Comparer {
                         compare_path,
                     );
 
-                    mx.drain_filter(|x| lines.contains(x));
-                });
+                    // lines are checked right away, so only the main file hashes are kept in memory
+                    for (line_num, combo) in reader.lines().enumerate() {
+                        let combo = match combo {
+                            Ok(combo) => combo,
+                            Err(err) => {
+                                eprintln!(
+                                    "Can't read combo on line {} in file {}. {}",
+                                    line_num,
+                                    compare_path.display(),
+                                    err
+                                );
+                                continue;
+                            }
+                        };
 
-            let main_lines = main_lines.into_inner().unwrap();
+                        total_lines.fetch_add(1, Ordering::Relaxed);
+
+                        let hash = seahash::hash(combo.as_bytes());
+                        // with attribution a line may be found in an earlier file than the one which matched it first
+                        main_lines.move_to(hash, &matched_lines, file_num, self.attribution);
+                    }
+                });
 
             println!("Сохранение...");
-            if !main_lines.is_empty() {
-                let file = match open_file_r(path) {
-                    Ok(file) => file,
+            let mut saved = 0usize;
+
+            for &op in self.op.outputs(self.emit) {
+                let no_results = match op {
+                    SetOp::Diff => main_lines.is_empty(),
+                    SetOp::Intersect => matched_lines.is_empty(),
+                    SetOp::Symdiff | SetOp::Union => false,
+                };
+
+                if no_results {
+                    continue;
+                }
+
+                let results_path = self.build_results_path(path, op.to_suffix());
+                let results_file = Some(utils::open_results_file(&results_path)?);
+
+                match self.save_results(op, path, &main_lines, &matched_lines, &results_file) {
+                    Ok(op_saved) => saved += op_saved,
                     Err(err) => {
                         eprintln!("Can't read input file {}. {}", path.display(), err);
+                        remove_results(results_file, &results_path);
                         continue;
                     }
-                };
-
-                let reader = utils::reader_from_file(file);
-
-                let suffix = self
-                    .task
-                    .to_suffix()
-                    .replace("{file}", self.compare_name.to_str().unwrap_or_default());
-                let results_path = utils::build_results_path(path, &self.results_path, &suffix);
-                let results_file = Some(utils::open_results_file(results_path)?);
-
-                let mut results = Vec::with_capacity(lines_count);
-
-                reader
-                    .lines()
-                    .enumerate()
-                    .filter_map(|(i, combo)| {
-                        if let Ok(combo) = combo {
-                            let hash = seahash::hash(combo.as_bytes());
-                            Some((i, combo, hash))
-                        } else {
-                            None
-                        }
-                    })
-                    .for_each(|(i, combo, hash)| {
-                        if main_lines.contains(&hash) {
-                            results.push(combo);
-                        }
+                }
 
-                        if results.len() == self.save_period || lines_count - i == 1 {
-                            if let Err(e) = utils::save_results(&mut results, &results_file) {
-                                eprintln!("Couldn't write to file: {}", e);
-                            }
-                        }
-                    });
+                if op == SetOp::Intersect && self.attribution {
+                    if let Err(err) = self.save_sources_report(path, &matched_lines) {
+                        eprintln!("Couldn't write to file: {}", err);
+                    }
+                }
             }
 
             println!(
-                "Итого {}/{} уникальных строк за {:?}\nФайлов для сравнения: {}. Всего строк: {}",
-                main_lines.len(),
+                "Итого {} строк ({} строк в основном файле, совпало {}) за {:?}\nФайлов для сравнения: {}. Всего строк: {}",
+                saved,
                 lines_count,
+                matched_lines.len(),
                 inner_now.elapsed(),
                 self.compare_with.len(),
                 total_lines.load(Ordering::Relaxed),
@@ -231,3 +298,598 @@ impl LinesProcessor for Comparer {
         Ok(())
     }
 }
+
+impl Comparer {
+    /// Merge-join of sorted target and compare files, memory doesn't depend on files size
+    fn process_sorted(&self) -> Result<(), CoreError> {
+        let now = time::Instant::now();
+
+        for (file_num, path) in self.targets.iter().enumerate() {
+            let inner_now = time::Instant::now();
+
+            println!("[{}/{}]Файл: {:?}", file_num + 1, self.targets.len(), path,);
+
+            let mut target = match SortedLines::open(path) {
+                Ok(target) => target,
+                Err(err) => {
+                    eprintln!("Can't read input file {}. {}", path.display(), err);
+                    continue;
+                }
+            };
+
+            let mut compare = MergedLines::open(&self.compare_with)?;
+
+            let mut outputs = Vec::new();
+            for &op in self.op.outputs(self.emit) {
+                let results_path = self.build_results_path(path, op.to_suffix());
+                outputs.push(SortedOutput {
+                    op,
+                    file: Some(utils::open_results_file(results_path)?),
+                    results: Vec::with_capacity(self.save_period),
+                    last: None,
+                    saved: 0,
+                });
+            }
+
+            println!("Сравнение отсортированных файлов...");
+
+            let mut target_line = target.next_line()?;
+            let mut compare_line = compare.next_line()?;
+            // target duplicates have to match the same compare line, so it's remembered
+            let mut last_matched: Option<String> = None;
+            let mut last_compare_only: Option<String> = None;
+
+            loop {
+                let merged = match (target_line.take(), compare_line.take()) {
+                    (None, None) => break,
+                    (Some(t), Some((c, source))) if t == c => {
+                        last_matched = Some(t.clone());
+                        target_line = target.next_line()?;
+                        compare_line = Some((c, source));
+                        Merged::Both(t, source)
+                    }
+                    (Some(t), None) => {
+                        target_line = target.next_line()?;
+                        Merged::Target(t)
+                    }
+                    (Some(t), Some((c, source))) if utils::cmp_encoded(&t, &c).is_lt() => {
+                        target_line = target.next_line()?;
+                        compare_line = Some((c, source));
+                        Merged::Target(t)
+                    }
+                    (t, Some((c, _))) => {
+                        target_line = t;
+                        compare_line = compare.next_line()?;
+
+                        if last_matched.as_ref() == Some(&c)
+                            || last_compare_only.as_ref() == Some(&c)
+                        {
+                            continue;
+                        }
+                        last_compare_only = Some(c.clone());
+                        Merged::Compare(c)
+                    }
+                };
+
+                for output in outputs.iter_mut() {
+                    self.push_sorted(output, &merged);
+                }
+            }
+
+            let mut saved = 0usize;
+            for output in outputs.iter_mut() {
+                if !output.results.is_empty() {
+                    if let Err(e) = utils::save_results(&mut output.results, &output.file, None) {
+                        eprintln!("Couldn't write to file: {}", e);
+                    }
+                }
+                saved += output.saved;
+            }
+
+            println!("Итого {} строк за {:?}", saved, inner_now.elapsed());
+        }
+
+        if self.targets.len() > 1 {
+            println!("Потрачено в общем: {:?}", now.elapsed());
+        }
+
+        Ok(())
+    }
+
+    fn push_sorted(&self, output: &mut SortedOutput, merged: &Merged) {
+        let (combo, source) = match (output.op, merged) {
+            (SetOp::Diff, Merged::Target(combo)) => (combo, None),
+            // like the hash comparison, symdiff has every line once
+            (SetOp::Symdiff, Merged::Target(combo)) => {
+                if output.last.as_ref() == Some(combo) {
+                    return;
+                }
+                output.last = Some(combo.clone());
+                (combo, None)
+            }
+            (SetOp::Symdiff, Merged::Compare(combo)) => (combo, None),
+            (SetOp::Intersect, Merged::Both(combo, source)) => {
+                let source = if self.attribution {
+                    Some(*source)
+                } else {
+                    None
+                };
+                (combo, source)
+            }
+            (
+                SetOp::Union,
+                Merged::Target(combo) | Merged::Both(combo, _) | Merged::Compare(combo),
+            ) => {
+                if output.last.as_ref() == Some(combo) {
+                    return;
+                }
+                output.last = Some(combo.clone());
+                (combo, None)
+            }
+            _ => return,
+        };
+
+        output.results.push(self.output_line(combo.clone(), source));
+        output.saved += 1;
+
+        if output.results.len() == self.save_period {
+            if let Err(e) = utils::save_results(&mut output.results, &output.file, None) {
+                eprintln!("Couldn't write to file: {}", e);
+            }
+        }
+    }
+
+    /// Memory-bounded comparison: the compare files are folded into a bloom filter,
+    /// with `exact` the filter hits are checked against the compare files once more
+    fn process_filtered(&self, fp_rate: f64) -> Result<(), CoreError> {
+        if matches!(self.op, SetOp::Symdiff | SetOp::Union) {
+            return Err(CoreError::FilterUnsupportedOp(self.op));
+        }
+
+        if self.attribution {
+            println!("Фильтр не хранит источники строк, атрибуция не используется");
+        }
+
+        let now = time::Instant::now();
+
+        println!("Подсчёт строк в файлах для сравнения...");
+
+        let compare_lines: usize = self
+            .compare_with
+            .par_iter()
+            .map(|compare_path| match open_file_r(compare_path) {
+                Ok(file) => utils::count_lines(file),
+                Err(err) => {
+                    eprintln!("Can't read input file {}. {}", compare_path.display(), err);
+                    0
+                }
+            })
+            .sum();
+
+        let filter = BloomFilter::with_rate(compare_lines, fp_rate);
+
+        println!(
+            "Построение фильтра для {} строк. Размер: {} байт...",
+            compare_lines,
+            filter.size()
+        );
+
+        self.compare_with.par_iter().for_each(|compare_path| {
+            let file = match open_file_r(compare_path) {
+                Ok(file) => file,
+                Err(err) => {
+                    eprintln!("Can't read input file {}. {}", compare_path.display(), err);
+                    return;
+                }
+            };
+
+            let reader = utils::reader_from_file(file);
+
+            for combo in reader.lines() {
+                let combo = if let Ok(combo) = combo {
+                    combo
+                } else {
+                    continue;
+                };
+
+                filter.insert(seahash::hash(combo.as_bytes()));
+            }
+        });
+
+        let expected_fp_rate = filter.expected_fp_rate();
+
+        for (file_num, path) in self.targets.iter().enumerate() {
+            let inner_now = time::Instant::now();
+
+            println!("[{}/{}]Файл: {:?}", file_num + 1, self.targets.len(), path,);
+
+            let confirmed = if self.exact {
+                let candidates = match self.filter_candidates(path, &filter) {
+                    Ok(candidates) => candidates,
+                    Err(err) => {
+                        eprintln!("Can't read input file {}. {}", path.display(), err);
+                        continue;
+                    }
+                };
+
+                println!("Проверка {} совпадений по фильтру...", candidates.len());
+
+                let confirmed = self.confirm_candidates(&candidates);
+                println!("Подтверждено совпадений: {}", confirmed.len());
+                Some(confirmed)
+            } else {
+                None
+            };
+
+            let matched = |hash: &u64| match &confirmed {
+                Some(confirmed) => confirmed.contains(hash),
+                None => filter.contains(*hash),
+            };
+
+            println!("Сохранение...");
+            let mut saved = 0usize;
+
+            for &op in self.op.outputs(self.emit) {
+                let results_path = self.build_results_path(path, op.to_suffix());
+                let results_file = Some(utils::open_results_file(&results_path)?);
+
+                let intersect = op == SetOp::Intersect;
+                match self.save_target_lines(
+                    path,
+                    &results_file,
+                    false,
+                    |hash| matched(hash) == intersect,
+                    |_| None,
+                ) {
+                    Ok(op_saved) => saved += op_saved,
+                    Err(err) => {
+                        eprintln!("Can't read input file {}. {}", path.display(), err);
+                        remove_results(results_file, &results_path);
+                        continue;
+                    }
+                }
+            }
+
+            println!(
+                "Итого {} строк за {:?}\nОжидаемая доля ложных срабатываний фильтра: {:.6}{}",
+                saved,
+                inner_now.elapsed(),
+                expected_fp_rate,
+                if self.exact {
+                    " (исправлено точной проверкой)"
+                } else {
+                    ""
+                },
+            );
+        }
+
+        if self.targets.len() > 1 {
+            println!("Потрачено в общем: {:?}", now.elapsed());
+        }
+
+        Ok(())
+    }
+
+    /// Hashes of the target lines which may be in the compare files according to the filter
+    fn filter_candidates(&self, path: &Path, filter: &BloomFilter) -> std::io::Result<NoHashSet> {
+        let file = open_file_r(path)?;
+        let reader = utils::reader_from_file(file);
+
+        let mut candidates = NoHashSet::default();
+
+        for combo in reader.lines() {
+            let combo = if let Ok(combo) = combo {
+                combo
+            } else {
+                continue;
+            };
+
+            let hash = seahash::hash(combo.as_bytes());
+            if filter.contains(hash) {
+                candidates.insert(hash);
+            }
+        }
+
+        Ok(candidates)
+    }
+
+    /// Candidates which really are in the compare files
+    fn confirm_candidates(&self, candidates: &NoHashSet) -> NoHashSet {
+        let confirmed: Mutex<NoHashSet> = Mutex::new(NoHashSet::default());
+
+        self.compare_with.par_iter().for_each(|compare_path| {
+            let file = match open_file_r(compare_path) {
+                Ok(file) => file,
+                Err(err) => {
+                    eprintln!("Can't read input file {}. {}", compare_path.display(), err);
+                    return;
+                }
+            };
+
+            let reader = utils::reader_from_file(file);
+
+            let found: Vec<u64> = reader
+                .lines()
+                .filter_map(|combo| {
+                    let hash = seahash::hash(combo.ok()?.as_bytes());
+                    candidates.contains(&hash).then_some(hash)
+                })
+                .collect();
+
+            confirmed.lock().unwrap().extend(found);
+        });
+
+        confirmed.into_inner().unwrap()
+    }
+
+    /// Writes the result of the set operation, `main_lines` holds hashes which are only in the target
+    /// and `matched_lines` holds hashes which are in both target and compare files
+    fn save_results(
+        &self,
+        op: SetOp,
+        path: &Path,
+        main_lines: &ShardedHashSet,
+        matched_lines: &ShardedHashMap,
+        results_file: &Option<File>,
+    ) -> std::io::Result<usize> {
+        // both sides of the symmetric difference are written without duplicates
+        let mut saved = match op {
+            SetOp::Diff | SetOp::Symdiff => self.save_target_lines(
+                path,
+                results_file,
+                op == SetOp::Symdiff,
+                |hash| main_lines.contains(hash),
+                |_| None,
+            )?,
+            SetOp::Intersect => self.save_target_lines(
+                path,
+                results_file,
+                false,
+                |hash| matched_lines.contains(hash),
+                |hash| {
+                    if self.attribution {
+                        matched_lines.get(hash)
+                    } else {
+                        None
+                    }
+                },
+            )?,
+            SetOp::Union => self.save_target_lines(path, results_file, true, |_| true, |_| None)?,
+        };
+
+        if matches!(op, SetOp::Symdiff | SetOp::Union) {
+            saved += self.save_compare_lines(results_file, |hash| {
+                !main_lines.contains(hash) && !matched_lines.contains(hash)
+            });
+        }
+
+        Ok(saved)
+    }
+
+    /// Writes lines of the target whose hashes satisfy `filter`,
+    /// the compare file returned by `source` is added after a tab
+    fn save_target_lines(
+        &self,
+        path: &Path,
+        results_file: &Option<File>,
+        dedup: bool,
+        filter: impl Fn(&u64) -> bool,
+        source: impl Fn(&u64) -> Option<usize>,
+    ) -> std::io::Result<usize> {
+        let file = open_file_r(path)?;
+        let reader = utils::reader_from_file(file);
+
+        let mut seen = NoHashSet::default();
+        let mut results = Vec::with_capacity(self.save_period);
+        let mut saved = 0usize;
+
+        for combo in reader.lines() {
+            let combo = if let Ok(combo) = combo {
+                combo
+            } else {
+                continue;
+            };
+
+            let hash = seahash::hash(combo.as_bytes());
+            if !filter(&hash) || (dedup && !seen.insert(hash)) {
+                continue;
+            }
+
+            results.push(self.output_line(combo, source(&hash)));
+            saved += 1;
+
+            if results.len() == self.save_period {
+                if let Err(e) = utils::save_results(&mut results, results_file, None) {
+                    eprintln!("Couldn't write to file: {}", e);
+                }
+            }
+        }
+
+        if !results.is_empty() {
+            if let Err(e) = utils::save_results(&mut results, results_file, None) {
+                eprintln!("Couldn't write to file: {}", e);
+            }
+        }
+
+        Ok(saved)
+    }
+
+    /// Writes how many matched lines were first found in each compare file
+    fn save_sources_report(
+        &self,
+        path: &Path,
+        matched_lines: &ShardedHashMap,
+    ) -> std::io::Result<()> {
+        let files_count = self.compare_with.len().max(1);
+        let mut counts: Vec<(usize, usize)> = matched_lines
+            .count_by_file(files_count)
+            .into_iter()
+            .enumerate()
+            .filter(|&(_, count)| count > 0)
+            .collect();
+        counts.sort_unstable_by_key(|&(_, count)| Reverse(count));
+
+        let mut results: Vec<String> = counts
+            .into_iter()
+            .map(|(file_num, count)| format!("{}\t{}", count, self.source_path(file_num).display()))
+            .collect();
+
+        let results_file = Some(utils::open_results_file(
+            self.build_results_path(path, "sources"),
+        )?);
+        utils::save_results(&mut results, &results_file, None)
+    }
+
+    /// Combo in the output format with the compare file it was found in first.
+    /// The combo is formatted before the file is added, so the file isn't taken for its field
+    fn output_line(&self, combo: String, source: Option<usize>) -> String {
+        let combo = self.parser.format_string(combo);
+        match source {
+            Some(file_num) => format!("{}\t{}", combo, self.source_path(file_num).display()),
+            None => combo,
+        }
+    }
+
+    /// Compare file by its number, the index is the only source when it's used
+    fn source_path(&self, file_num: usize) -> &Path {
+        match &self.compare_index {
+            Some(index_path) => index_path,
+            None => &self.compare_with[file_num],
+        }
+    }
+
+    fn build_results_path(&self, path: &Path, op_suffix: &str) -> PathBuf {
+        let suffix = self
+            .task
+            .to_suffix()
+            .replace("{op}", op_suffix)
+            .replace("{file}", self.compare_name.to_str().unwrap_or_default());
+        self.layout
+            .build_results_path(path, &self.results_path, &suffix)
+    }
+
+    /// Writes lines of the compare files whose hashes satisfy `filter`, without duplicates
+    fn save_compare_lines(
+        &self,
+        results_file: &Option<File>,
+        filter: impl Fn(&u64) -> bool,
+    ) -> usize {
+        let mut seen = NoHashSet::default();
+        let mut results = Vec::with_capacity(self.save_period);
+        let mut saved = 0usize;
+
+        for compare_path in self.compare_with.iter() {
+            let file = match open_file_r(compare_path) {
+                Ok(file) => file,
+                Err(err) => {
+                    eprintln!("Can't read input file {}. {}", compare_path.display(), err);
+                    continue;
+                }
+            };
+
+            let reader = utils::reader_from_file(file);
+
+            for combo in reader.lines() {
+                let combo = if let Ok(combo) = combo {
+                    combo
+                } else {
+                    continue;
+                };
+
+                let hash = seahash::hash(combo.as_bytes());
+                if !filter(&hash) || !seen.insert(hash) {
+                    continue;
+                }
+
+                results.push(combo);
+                saved += 1;
+
+                if results.len() == self.save_period {
+                    if let Err(e) =
+                        utils::save_results(&mut results, results_file, Some(&self.parser))
+                    {
+                        eprintln!("Couldn't write to file: {}", e);
+                    }
+                }
+            }
+        }
+
+        if !results.is_empty() {
+            if let Err(e) = utils::save_results(&mut results, results_file, Some(&self.parser)) {
+                eprintln!("Couldn't write to file: {}", e);
+            }
+        }
+
+        saved
+    }
+}
+
+/// Removes the results file which couldn't be written
+fn remove_results(results_file: Option<File>, results_path: &Path) {
+    drop(results_file);
+    if let Err(err) = fs::remove_file(results_path) {
+        eprintln!("Can't remove file {}. {}", results_path.display(), err);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{ffi::OsString, fs, path::Path};
+
+    use super::Comparer;
+    use crate::{
+        cmd,
+        core::{lines_processor::LinesProcessor, utils},
+    };
+
+    /// Sorted lines of all results of comparing `target` with `with`
+    fn compare(dir: &Path, name: &str, target: &[u8], with: &[u8], args: &[&str]) -> Vec<String> {
+        let target_path = dir.join(format!("{}.txt", name));
+        let with_path = dir.join(format!("{}_with.txt", name));
+        fs::write(&target_path, target).unwrap();
+        fs::write(&with_path, with).unwrap();
+        let results_path = dir.join(format!("{}_results", name));
+
+        let args = ["combosutils", "--task", "compare"]
+            .iter()
+            .chain(args)
+            .map(OsString::from)
+            .chain([
+                OsString::from("--target"),
+                target_path.into_os_string(),
+                OsString::from("--with"),
+                with_path.into_os_string(),
+            ]);
+        Comparer::new(cmd::parse_args_from(args).unwrap(), results_path.clone(), 2)
+            .process()
+            .unwrap();
+
+        let mut lines = Vec::new();
+        for results in utils::test_results(&results_path) {
+            lines.extend(utils::read_decoded_lines(&results).unwrap());
+        }
+        lines.sort();
+        lines
+    }
+
+    #[test]
+    fn symdiff_has_target_duplicates_once() {
+        let dir = utils::test_dir("compare_symdiff");
+        let target = b"a:1\nb:2\nb:2\nb:2\nc:3\n";
+        let with = b"c:3\nd:4\nd:4\n";
+        let expected = ["a:1", "b:2", "d:4"];
+
+        let hashed = compare(&dir, "hashed", target, with, &["--op", "symdiff"]);
+        assert_eq!(hashed, expected);
+        let sorted = compare(
+            &dir,
+            "sorted",
+            target,
+            with,
+            &["--op", "symdiff", "--sorted"],
+        );
+        assert_eq!(sorted, expected);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -4,7 +4,10 @@ use rustc_hash::FxHashSet;
 
 use crate::{
     cmd::Args,
-    core::{lines_processor::LinesProcessor, task::Task, utils},
+    core::{
+        combo::ComboParser, lines_processor::LinesProcessor, results_layout::ResultsLayout,
+        task::Task, utils,
+    },
     errors::core_error::CoreError,
 };
 
@@ -12,6 +15,8 @@ pub struct DuplicatesRemoverMem {
     targets: Vec<PathBuf>,
     results_path: PathBuf,
     task: Task,
+    layout: ResultsLayout,
+    parser: ComboParser,
 }
 
 impl LinesProcessor for DuplicatesRemoverMem {
@@ -20,10 +25,12 @@ impl LinesProcessor for DuplicatesRemoverMem {
             targets: args.targets,
             results_path,
             task: args.task,
+            layout: args.layout,
+            parser: args.combo,
         }
     }
 
-    fn process_line(_: &str) -> Option<String> {
+    fn process_line(&self, _: &str) -> Option<String> {
         unreachable!()
     }
 
@@ -63,14 +70,16 @@ impl LinesProcessor for DuplicatesRemoverMem {
             println!("Строк после удаления: {}", lines_count_after);
             println!("Сохранение...");
 
-            // TODO: handle files with the same names but in a different dirs
             let results_path =
-                utils::build_results_path(path, &self.results_path, self.task.to_suffix());
+                self.layout
+                    .build_results_path(path, &self.results_path, self.task.to_suffix());
             let mut results_file = utils::open_results_file(results_path)?;
 
-            if let Err(e) =
-                utils::save_results_hashset(&mut lines.iter().copied(), &mut results_file)
-            {
+            if let Err(e) = utils::save_results_hashset(
+                &mut lines.iter().copied(),
+                &mut results_file,
+                &self.parser,
+            ) {
                 eprintln!("Couldn't save results to file: {}", e);
                 continue;
             }
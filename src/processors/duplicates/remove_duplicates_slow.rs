@@ -8,7 +8,9 @@ use rayon::prelude::*;
 use crate::{
     cmd::Args,
     core::{
+        combo::ComboParser,
         lines_processor::LinesProcessor,
+        results_layout::ResultsLayout,
         task::Task,
         utils::{self, count_lines, open_file_r},
     },
@@ -20,6 +22,8 @@ pub struct DuplicatesRemoverSlow {
     results_path: PathBuf,
     save_period: usize,
     task: Task,
+    layout: ResultsLayout,
+    parser: ComboParser,
 }
 
 impl LinesProcessor for DuplicatesRemoverSlow {
@@ -29,10 +33,12 @@ impl LinesProcessor for DuplicatesRemoverSlow {
             results_path,
             save_period,
             task: args.task,
+            layout: args.layout,
+            parser: args.combo,
         }
     }
 
-    fn process_line(_: &str) -> Option<String> {
+    fn process_line(&self, _: &str) -> Option<String> {
         unreachable!()
     }
 
@@ -101,9 +107,9 @@ impl LinesProcessor for DuplicatesRemoverSlow {
                     hashes.lock().unwrap().insert(hash);
                 });
 
-            // TODO: handle files with the same names but in a different dirs
             let results_path =
-                utils::build_results_path(path, &self.results_path, self.task.to_suffix());
+                self.layout
+                    .build_results_path(path, &self.results_path, self.task.to_suffix());
             let results_file = Some(utils::open_results_file(results_path)?);
             let mut results = Vec::with_capacity(lines_count);
 
@@ -136,7 +142,9 @@ impl LinesProcessor for DuplicatesRemoverSlow {
                 }
 
                 if results.len() == self.save_period || lines_count - i == 1 {
-                    if let Err(e) = utils::save_results(&mut results, &results_file) {
+                    if let Err(e) =
+                        utils::save_results(&mut results, &results_file, Some(&self.parser))
+                    {
                         eprintln!("Couldn't write to file: {}", e);
                     }
                 }
@@ -0,0 +1,394 @@
+use std::{fs::File, io::BufRead, ops::Range, path::PathBuf, time};
+
+use regex::Regex;
+
+use crate::{
+    cmd::Args,
+    core::{
+        combo::{Combo, ComboParser},
+        lines_processor::LinesProcessor,
+        results_layout::ResultsLayout,
+        task::Task,
+        utils::{self, open_file_r},
+    },
+    errors::core_error::CoreError,
+    processors::extract_phones::extract_phone,
+};
+
+const EMAIL_PATTERN: &str = r"[\w.+-]+@[\w-]+(?:\.[\w-]+)+";
+const PHONE_PATTERN: &str = r"(?:^|[^\w@.+])(?P<login>\+?\d[\d()-]{6,20}\d)";
+/// `{}` is replaced with the single-character separators, so the password stops before them
+const PASSWORD_PATTERN: &str = r"(?P<password>[^\s{}]+)";
+/// Punctuation which ends sentences, it's not a part of the password when the combo is in one.
+/// `!` and `?` are kept, passwords end with them too often
+const SENTENCE_PUNCTUATION: [char; 3] = ['.', ',', ';'];
+/// Closing brackets and quotes with their opening ones
+const CLOSING_PUNCTUATION: [(char, char); 5] =
+    [(')', '('), (']', '['), ('}', '{'), ('"', '"'), ('\'', '\'')];
+
+/// Finds combos inside of any text. Every pattern has `login` and `password` groups
+/// and may have the `separator` group, matches of the earlier patterns win when they overlap
+pub struct ComboExtractor {
+    targets: Vec<PathBuf>,
+    results_path: PathBuf,
+    save_period: usize,
+    task: Task,
+    layout: ResultsLayout,
+    parser: ComboParser,
+    patterns: Vec<Regex>,
+    /// Separators which end the passwords of the default patterns, empty with custom patterns
+    separators: Vec<String>,
+}
+
+impl LinesProcessor for ComboExtractor {
+    fn new(args: Args, results_path: PathBuf, save_period: usize) -> Self {
+        let (patterns, separators) = if args.patterns.is_empty() {
+            let separators = args.combo.separators().to_vec();
+            (default_patterns(&separators), separators)
+        } else {
+            (args.patterns, Vec::new())
+        };
+
+        ComboExtractor {
+            targets: args.targets,
+            results_path,
+            save_period,
+            task: args.task,
+            layout: args.layout,
+            parser: args.combo,
+            patterns,
+            separators,
+        }
+    }
+
+    fn process_line(&self, _: &str) -> Option<String> {
+        unreachable!()
+    }
+
+    fn process(self) -> Result<(), CoreError> {
+        println!(
+            "Обработка {} файлов. Шаблонов: {}",
+            self.targets.len(),
+            self.patterns.len()
+        );
+
+        let now = time::Instant::now();
+
+        let mut results: Vec<String> = Vec::with_capacity(self.save_period);
+        let mut total_count = 0usize;
+
+        for (file_num, path) in self.targets.iter().enumerate() {
+            let inner_now = time::Instant::now();
+
+            let file = match open_file_r(path) {
+                Ok(file) => file,
+                Err(err) => {
+                    eprintln!("Can't read input file {}. {}", path.display(), err);
+                    continue;
+                }
+            };
+
+            println!(
+                "[{}/{}]Файл: {}",
+                file_num + 1,
+                self.targets.len(),
+                path.display()
+            );
+
+            let reader = utils::reader_from_file(file);
+
+            let results_path =
+                self.layout
+                    .build_results_path(path, &self.results_path, self.task.to_suffix());
+            let mut results_file: Option<File> = None;
+            let mut matches_count = 0usize;
+
+            for (i, line) in reader.lines().enumerate() {
+                let line = match line {
+                    Ok(line) => line,
+                    Err(err) => {
+                        eprintln!(
+                            "Can't read combo on line {} in file {}. {}",
+                            i,
+                            path.display(),
+                            err
+                        );
+                        continue;
+                    }
+                };
+
+                let combos = self.extract(&line);
+                if combos.is_empty() {
+                    continue;
+                }
+                if results_file.is_none() {
+                    results_file = Some(utils::open_results_file(&results_path)?);
+                }
+
+                matches_count += combos.len();
+                results.extend(combos);
+
+                if results.len() >= self.save_period {
+                    if let Err(e) = utils::save_results(&mut results, &results_file, None) {
+                        eprintln!("Couldn't write to file: {}", e);
+                    }
+                }
+            }
+
+            if let Err(e) = utils::save_results(&mut results, &results_file, None) {
+                eprintln!("Couldn't write to file: {}", e);
+            }
+            total_count += matches_count;
+
+            println!(
+                "Найдено комбо: {}. Потрачено: {:?}",
+                matches_count,
+                inner_now.elapsed()
+            );
+        }
+
+        if self.targets.len() > 1 {
+            println!(
+                "Найдено комбо в общем: {}. Потрачено в общем: {:?}",
+                total_count,
+                now.elapsed()
+            );
+        }
+
+        Ok(())
+    }
+}
+
+impl ComboExtractor {
+    /// Normalized combos of all matches in the line
+    fn extract(&self, line: &str) -> Vec<String> {
+        let mut found: Vec<Range<usize>> = Vec::new();
+        let mut combos = Vec::new();
+
+        for pattern in &self.patterns {
+            let mut start = 0;
+            while start <= line.len() {
+                let captures = match pattern.captures(&line[start..]) {
+                    Some(captures) => captures,
+                    None => break,
+                };
+                let offset = start;
+                let whole = captures.get(0).unwrap();
+                start = offset + whole.end();
+                if whole.start() == whole.end() {
+                    start += line[start..].chars().next().map_or(1, char::len_utf8);
+                }
+
+                let (login, password) = match (captures.name("login"), captures.name("password")) {
+                    (Some(login), Some(password)) => (login, password),
+                    _ => continue,
+                };
+                let password_start = offset + password.start();
+                let (password_end, glued) = self.split_password(
+                    line,
+                    offset + login.start(),
+                    password_start,
+                    offset + password.end(),
+                );
+                if !self.separators.is_empty() {
+                    // the rest of the match may have other combos
+                    start = glued.unwrap_or(password_end);
+                }
+
+                let range = offset + login.start()..password_end;
+                if found
+                    .iter()
+                    .any(|other| range.start < other.end && other.start < range.end)
+                {
+                    continue;
+                }
+
+                let combo = Combo {
+                    login: login.as_str().trim(),
+                    password: line[password_start..password_end].trim(),
+                    extra: Vec::new(),
+                    separator: captures.name("separator").map_or(":", |m| m.as_str()),
+                    fields: Vec::new(),
+                };
+                if !combo.is_valid() {
+                    continue;
+                }
+
+                let login = match normalize_login(combo.login) {
+                    Some(login) => login,
+                    None => continue,
+                };
+                found.push(range);
+                combos.push(self.parser.join(&combo, &login, combo.password));
+            }
+        }
+
+        combos
+    }
+
+    /// End of the password found by a default pattern without the text after a separator
+    /// and the login of the next combo glued to it, which position is returned too
+    fn split_password(
+        &self,
+        line: &str,
+        login_start: usize,
+        start: usize,
+        end: usize,
+    ) -> (usize, Option<usize>) {
+        if self.separators.is_empty() {
+            return (end, None);
+        }
+
+        let mut password = &line[start..end];
+        for separator in &self.separators {
+            if let Some(pos) = password.find(separator.as_str()) {
+                password = &password[..pos];
+            }
+        }
+
+        let mut glued = None;
+        let rest = line[start + password.len()..].trim_start_matches([' ', '\t']);
+        if self
+            .separators
+            .iter()
+            .any(|separator| rest.starts_with(separator.as_str()))
+        {
+            let login_start = password
+                .char_indices()
+                .rev()
+                .find(|&(_, c)| !(c.is_alphanumeric() || "_.+-@()".contains(c)));
+            if let Some((glue, glue_char)) = login_start {
+                let login = &password[glue + glue_char.len_utf8()..];
+                if glue > 0 && is_login(login) {
+                    glued = Some(start + glue + glue_char.len_utf8());
+                    password = &password[..glue];
+                }
+            }
+        }
+
+        if glued.is_none() {
+            password = trim_punctuation(line, login_start, start, password);
+        }
+        (start + password.len(), glued)
+    }
+}
+
+/// Password without the punctuation of the text around the combo. Passwords end with
+/// punctuation too, so it's cut only before whitespace or the end of the line: closing
+/// brackets and quotes when the combo is opened with them, `.`, `,` and `;` when there is
+/// text before the combo or after the punctuation
+fn trim_punctuation<'a>(
+    line: &str,
+    login_start: usize,
+    start: usize,
+    password: &'a str,
+) -> &'a str {
+    let after = &line[start + password.len()..];
+    if matches!(after.chars().next(), Some(c) if !c.is_whitespace()) {
+        return password;
+    }
+
+    let before = &line[..login_start];
+    let sentence = !before.trim().is_empty() || !after.trim().is_empty();
+    let mut password = password;
+    while let Some(c) = password.chars().last() {
+        let trailing = if SENTENCE_PUNCTUATION.contains(&c) {
+            sentence
+        } else {
+            CLOSING_PUNCTUATION
+                .iter()
+                .any(|&(closing, opening)| closing == c && before.ends_with(opening))
+        };
+        if !trailing {
+            break;
+        }
+        password = &password[..password.len() - c.len_utf8()];
+    }
+    password
+}
+
+/// Email or phone which starts the next combo
+fn is_login(login: &str) -> bool {
+    if login.contains('@') {
+        return true;
+    }
+    login
+        .chars()
+        .all(|c| c.is_ascii_digit() || matches!(c, '+' | '(' | ')' | '-'))
+        && extract_phone(login).is_some()
+}
+
+/// Emails are lowercased and phones are in the international format, other logins are kept.
+/// `None` for numbers which aren't phones
+fn normalize_login(login: &str) -> Option<String> {
+    if login.contains('@') {
+        return Some(login.to_lowercase());
+    }
+    if login
+        .chars()
+        .all(|c| c.is_ascii_digit() || matches!(c, '+' | '(' | ')' | '-'))
+    {
+        return extract_phone(login);
+    }
+    Some(login.to_owned())
+}
+
+/// Email or phone, one of the separators and the password after it
+fn default_patterns(separators: &[String]) -> Vec<Regex> {
+    let single_chars: String = separators
+        .iter()
+        .filter(|separator| separator.chars().count() == 1)
+        .map(|separator| regex::escape(separator))
+        .collect();
+    let password = PASSWORD_PATTERN.replace("{}", &single_chars);
+    let separators = separators
+        .iter()
+        .map(|separator| regex::escape(separator))
+        .collect::<Vec<String>>()
+        .join("|");
+    let tail = format!(r"[ \t]*(?P<separator>{})[ \t]*{}", separators, password);
+
+    vec![
+        Regex::new(&format!(r"(?P<login>{}){}", EMAIL_PATTERN, tail)).unwrap(),
+        Regex::new(&format!("{}{}", PHONE_PATTERN, tail)).unwrap(),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::ComboExtractor;
+    use crate::{cmd, core::lines_processor::LinesProcessor};
+
+    fn extract(line: &str) -> Vec<String> {
+        let args = [
+            "combosutils",
+            "--task",
+            "extract-combos",
+            "--target",
+            "combos.txt",
+        ];
+        ComboExtractor::new(cmd::parse_args_from(args).unwrap(), PathBuf::new(), 1).extract(line)
+    }
+
+    #[test]
+    fn passwords_keep_their_punctuation() {
+        assert_eq!(extract("a@b.c:qwerty."), ["a@b.c:qwerty."]);
+        assert_eq!(extract("a@b.c:pass)"), ["a@b.c:pass)"]);
+        assert_eq!(extract("a@b.c:'pass'"), ["a@b.c:'pass'"]);
+        assert_eq!(
+            extract("a@b.c:pa.ss, d@e.f:word"),
+            ["a@b.c:pa.ss", "d@e.f:word"]
+        );
+        assert_eq!(extract("a@b.c:pass.word and more"), ["a@b.c:pass.word"]);
+    }
+
+    #[test]
+    fn sentence_punctuation_is_cut() {
+        assert_eq!(extract("my login is a@b.c:qwerty."), ["a@b.c:qwerty"]);
+        assert_eq!(extract("a@b.c:qwerty. That's it"), ["a@b.c:qwerty"]);
+        assert_eq!(extract("creds (a@b.c:pass)."), ["a@b.c:pass"]);
+        assert_eq!(extract("\"a@b.c:pass\" leaked"), ["a@b.c:pass"]);
+    }
+}
@@ -3,7 +3,9 @@ use std::{io::BufRead, path::PathBuf, time};
 use crate::{
     cmd::Args,
     core::{
+        combo::ComboParser,
         lines_processor::LinesProcessor,
+        results_layout::ResultsLayout,
         task::Task,
         utils::{self, open_file_r},
     },
@@ -15,6 +17,8 @@ pub struct PartExtractor {
     results_path: PathBuf,
     save_period: usize,
     task: Task,
+    layout: ResultsLayout,
+    parser: ComboParser,
 }
 
 impl LinesProcessor for PartExtractor {
@@ -24,10 +28,12 @@ impl LinesProcessor for PartExtractor {
             results_path,
             save_period,
             task: args.task,
+            layout: args.layout,
+            parser: args.combo,
         }
     }
 
-    fn process_line(_: &str) -> Option<String> {
+    fn process_line(&self, _: &str) -> Option<String> {
         unreachable!()
     }
 
@@ -69,9 +75,9 @@ impl LinesProcessor for PartExtractor {
 
             let reader = utils::reader_from_file(file);
 
-            // TODO: handle files with the same names but in a different dirs
             let results_path =
-                utils::build_results_path(path, &self.results_path, self.task.to_suffix());
+                self.layout
+                    .build_results_path(path, &self.results_path, self.task.to_suffix());
             let results_file = Some(utils::open_results_file(results_path)?);
 
             for (i, combo) in reader.lines().enumerate() {
@@ -88,13 +94,13 @@ impl LinesProcessor for PartExtractor {
                     }
                 };
 
-                let part = extract(&combo, self.task);
+                let part = extract(&self.parser, &combo, self.task);
                 if let Some(combo) = part {
                     results.push(combo);
                 }
 
                 if results.len() == self.save_period || lines_count - i == 1 {
-                    if let Err(e) = utils::save_results(&mut results, &results_file) {
+                    if let Err(e) = utils::save_results(&mut results, &results_file, None) {
                         eprintln!("Couldn't write to file: {}", e);
                     }
                 }
@@ -111,15 +117,12 @@ impl LinesProcessor for PartExtractor {
     }
 }
 
-fn extract(combo: &str, task: Task) -> Option<String> {
-    let (email, password) = combo.split_once([':', ';'])?;
-    if email.is_empty() || password.is_empty() {
-        return None;
-    }
+fn extract(parser: &ComboParser, line: &str, task: Task) -> Option<String> {
+    let combo = parser.parse(line)?;
 
     match task {
-        Task::ExtractLogins => Some(email.to_owned()),
-        Task::ExtractPasswords => Some(password.to_owned()),
+        Task::ExtractLogins => Some(combo.login.to_owned()),
+        Task::ExtractPasswords => Some(combo.password.to_owned()),
         _ => unreachable!(),
     }
 }
@@ -3,7 +3,9 @@ use std::{fs::File, io::BufRead, path::PathBuf, time};
 use crate::{
     cmd::Args,
     core::{
+        combo::ComboParser,
         lines_processor::LinesProcessor,
+        results_layout::ResultsLayout,
         task::Task,
         utils::{self, open_file_r},
     },
@@ -18,6 +20,8 @@ pub struct PhonesExtractor {
     results_path: PathBuf,
     save_period: usize,
     task: Task,
+    layout: ResultsLayout,
+    parser: ComboParser,
 }
 
 impl LinesProcessor for PhonesExtractor {
@@ -27,18 +31,15 @@ impl LinesProcessor for PhonesExtractor {
             results_path,
             save_period,
             task: args.task,
+            layout: args.layout,
+            parser: args.combo,
         }
     }
 
-    fn process_line(combo: &str) -> Option<String> {
-        let (phone, password) = combo.split_once([':', ';'])?;
-        if phone.is_empty() || password.is_empty() {
-            return None;
-        }
-        let mut combo = extract_phone(phone)?;
-        combo.push(':');
-        combo.push_str(password);
-        Some(combo)
+    fn process_line(&self, line: &str) -> Option<String> {
+        let combo = self.parser.parse(line)?;
+        let phone = extract_phone(combo.login)?;
+        Some(self.parser.join(&combo, &phone, combo.password))
     }
 
     fn process(self) -> Result<(), CoreError> {
@@ -79,9 +80,9 @@ impl LinesProcessor for PhonesExtractor {
 
             let reader = utils::reader_from_file(file);
 
-            // TODO: handle files with the same names but in a different dirs
             let results_path =
-                utils::build_results_path(path, &self.results_path, self.task.to_suffix());
+                self.layout
+                    .build_results_path(path, &self.results_path, self.task.to_suffix());
             let mut results_file: Option<File> = None;
 
             for (i, combo) in reader.lines().enumerate() {
@@ -98,7 +99,7 @@ impl LinesProcessor for PhonesExtractor {
                     }
                 };
 
-                let combo = PhonesExtractor::process_line(&combo);
+                let combo = self.process_line(&combo);
                 if results_file.is_none() && combo.is_some() {
                     results_file = Some(utils::open_results_file(&results_path)?);
                 }
@@ -108,7 +109,7 @@ impl LinesProcessor for PhonesExtractor {
                 }
 
                 if results.len() == self.save_period || lines_count - i == 1 {
-                    if let Err(e) = utils::save_results(&mut results, &results_file) {
+                    if let Err(e) = utils::save_results(&mut results, &results_file, None) {
                         eprintln!("Couldn't write to file: {}", e);
                     }
                 }
@@ -125,7 +126,8 @@ impl LinesProcessor for PhonesExtractor {
     }
 }
 
-fn extract_phone(phone: &str) -> Option<String> {
+/// Phone in the international format without `+`, `None` when the login is not a phone
+pub fn extract_phone(phone: &str) -> Option<String> {
     if phone.contains('@') {
         return None;
     }
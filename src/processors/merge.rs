@@ -1,20 +1,114 @@
-use std::{io::BufRead, path::PathBuf, time};
+use std::{
+    fs::File,
+    io::{BufRead, BufReader, Lines},
+    path::{Path, PathBuf},
+    time,
+};
+
+use encoding_rs_io::DecodeReaderBytes;
 
 use crate::{
     cmd::Args,
     core::{
+        combo::ComboParser,
         lines_processor::LinesProcessor,
+        results_layout::ResultsLayout,
         task::Task,
-        utils::{self, open_file_r},
+        utils::{self, open_file_r, NoHashSet},
     },
     errors::core_error::CoreError,
 };
 
+/// Joins targets into one file. Every line is written with its own newline,
+/// so a file without the trailing one doesn't glue its last line to the next file
 pub struct Merger {
     targets: Vec<PathBuf>,
     results_path: PathBuf,
     save_period: usize,
     task: Task,
+    layout: ResultsLayout,
+    parser: ComboParser,
+    dedup: bool,
+    interleave: bool,
+    output: Option<String>,
+}
+
+/// Opened target which is read line by line
+struct MergeInput<'a> {
+    path: &'a Path,
+    lines: Lines<BufReader<DecodeReaderBytes<File, Vec<u8>>>>,
+    line_num: usize,
+}
+
+impl<'a> MergeInput<'a> {
+    fn open(path: &'a Path) -> Option<Self> {
+        match open_file_r(path) {
+            Ok(file) => Some(MergeInput {
+                path,
+                lines: utils::reader_from_file(file).lines(),
+                line_num: 0,
+            }),
+            Err(err) => {
+                eprintln!("Can't read input file {}. {}", path.display(), err);
+                None
+            }
+        }
+    }
+
+    fn next_line(&mut self) -> Option<String> {
+        for combo in self.lines.by_ref() {
+            self.line_num += 1;
+
+            match combo {
+                Ok(combo) => return Some(combo),
+                Err(err) => eprintln!(
+                    "Can't read combo on line {} in file {}. {}",
+                    self.line_num,
+                    self.path.display(),
+                    err
+                ),
+            }
+        }
+
+        None
+    }
+}
+
+/// Output file with the lines waiting to be written
+struct MergeOutput<'a> {
+    results: Vec<String>,
+    results_file: Option<File>,
+    parser: &'a ComboParser,
+    seen: Option<NoHashSet>,
+    save_period: usize,
+    written: usize,
+    duplicates: usize,
+}
+
+impl MergeOutput<'_> {
+    fn push(&mut self, combo: String) {
+        if let Some(seen) = &mut self.seen {
+            if !seen.insert(seahash::hash(combo.as_bytes())) {
+                self.duplicates += 1;
+                return;
+            }
+        }
+
+        self.results.push(combo);
+        self.written += 1;
+
+        if self.results.len() == self.save_period {
+            self.save();
+        }
+    }
+
+    fn save(&mut self) {
+        if let Err(e) =
+            utils::save_results(&mut self.results, &self.results_file, Some(self.parser))
+        {
+            eprintln!("Couldn't write to file: {}", e);
+        }
+    }
 }
 
 impl LinesProcessor for Merger {
@@ -24,10 +118,15 @@ impl LinesProcessor for Merger {
             results_path,
             save_period,
             task: args.task,
+            layout: args.layout,
+            parser: args.combo,
+            dedup: args.dedup,
+            interleave: args.interleave,
+            output: args.output,
         }
     }
 
-    fn process_line(_: &str) -> Option<String> {
+    fn process_line(&self, _: &str) -> Option<String> {
         unreachable!()
     }
 
@@ -36,69 +135,89 @@ impl LinesProcessor for Merger {
 
         let now = time::Instant::now();
 
-        let mut results: Vec<String> = Vec::with_capacity(self.save_period);
+        let results_path = match &self.output {
+            Some(name) => self.results_path.join(name),
+            None => self.layout.build_results_path(
+                &self.targets[0],
+                &self.results_path,
+                self.task.to_suffix(),
+            ),
+        };
+
+        let mut output = MergeOutput {
+            results: Vec::with_capacity(self.save_period),
+            results_file: Some(utils::open_results_file(results_path)?),
+            parser: &self.parser,
+            seen: self.dedup.then(NoHashSet::default),
+            save_period: self.save_period,
+            written: 0,
+            duplicates: 0,
+        };
+
+        if self.interleave {
+            self.merge_interleaved(&mut output);
+        } else {
+            self.merge_sequential(&mut output);
+        }
+
+        output.save();
+
+        if self.dedup {
+            println!(
+                "Строк: {}. Пропущено дубликатов: {}",
+                output.written, output.duplicates
+            );
+        } else {
+            println!("Строк: {}", output.written);
+        }
 
-        let results_path =
-            utils::build_results_path(&self.targets[0], &self.results_path, self.task.to_suffix());
-        let results_file = Some(utils::open_results_file(results_path)?);
+        if self.targets.len() > 1 {
+            println!("Потрачено в общем: {:?}", now.elapsed());
+        }
+
+        Ok(())
+    }
+}
 
+impl Merger {
+    fn merge_sequential(&self, output: &mut MergeOutput) {
         for (file_num, path) in self.targets.iter().enumerate() {
-            let file = match open_file_r(path) {
-                Ok(file) => file,
-                Err(err) => {
-                    eprintln!("Can't read input file {}. {}", path.display(), err);
-                    continue;
-                }
+            let mut input = match MergeInput::open(path) {
+                Some(input) => input,
+                None => continue,
             };
 
-            let lines_count = utils::count_lines(file);
-
             println!(
-                "[{}/{}]Файл: {}. Строк: {}",
+                "[{}/{}]Файл: {}",
                 file_num + 1,
                 self.targets.len(),
-                path.display(),
-                lines_count
+                path.display()
             );
 
-            let file = match open_file_r(path) {
-                Ok(file) => file,
-                Err(err) => {
-                    eprintln!("Can't read input file {}. {}", path.display(), err);
-                    continue;
-                }
-            };
-
-            let reader = utils::reader_from_file(file);
-
-            for (i, combo) in reader.lines().enumerate() {
-                let combo = match combo {
-                    Ok(combo) => combo,
-                    Err(err) => {
-                        eprintln!(
-                            "Can't read combo on line {} in file {}. {}",
-                            i,
-                            path.display(),
-                            err
-                        );
-                        continue;
-                    }
-                };
-
-                results.push(combo);
-
-                if results.len() == self.save_period || lines_count - i == 1 {
-                    if let Err(e) = utils::save_results(&mut results, &results_file) {
-                        eprintln!("Couldn't write to file: {}", e);
-                    }
-                }
+            while let Some(combo) = input.next_line() {
+                output.push(combo);
             }
         }
+    }
 
-        if self.targets.len() > 1 {
-            println!("Потрачено в общем: {:?}", now.elapsed());
+    /// Takes one line from every target in turn until all of them end
+    fn merge_interleaved(&self, output: &mut MergeOutput) {
+        let mut inputs: Vec<MergeInput> = self
+            .targets
+            .iter()
+            .filter_map(|path| MergeInput::open(path))
+            .collect();
+
+        println!("Чередование строк из {} файлов", inputs.len());
+
+        while !inputs.is_empty() {
+            inputs.retain_mut(|input| match input.next_line() {
+                Some(combo) => {
+                    output.push(combo);
+                    true
+                }
+                None => false,
+            });
         }
-
-        Ok(())
     }
 }
@@ -1,9 +1,17 @@
+pub mod build_index;
 pub mod compare;
 pub mod duplicates;
+pub mod extract_combos;
 pub mod extract_logins_passwords;
 pub mod extract_phones;
 pub mod merge;
 pub mod remove_domain;
+pub mod sample;
+pub mod shard;
 pub mod shuffle;
+pub mod slice;
+pub mod sort;
+pub mod split_by_key;
 pub mod split_by_lines;
 pub mod split_by_parts;
+pub mod split_by_size;
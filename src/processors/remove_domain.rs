@@ -3,7 +3,9 @@ use std::{io::BufRead, path::PathBuf, time};
 use crate::{
     cmd::Args,
     core::{
+        combo::ComboParser,
         lines_processor::LinesProcessor,
+        results_layout::ResultsLayout,
         task::Task,
         utils::{self, open_file_r},
     },
@@ -15,6 +17,8 @@ pub struct DomainRemover {
     results_path: PathBuf,
     save_period: usize,
     task: Task,
+    layout: ResultsLayout,
+    parser: ComboParser,
 }
 
 impl LinesProcessor for DomainRemover {
@@ -24,11 +28,19 @@ impl LinesProcessor for DomainRemover {
             results_path,
             save_period,
             task: args.task,
+            layout: args.layout,
+            parser: args.combo,
         }
     }
 
-    fn process_line(line: &str) -> Option<String> {
-        remove_domain(line)
+    fn process_line(&self, line: &str) -> Option<String> {
+        let combo = self.parser.parse(line)?;
+
+        combo
+            .login
+            .split('@')
+            .next()
+            .map(|username| self.parser.join(&combo, username, combo.password))
     }
 
     fn process(self) -> Result<(), CoreError> {
@@ -69,9 +81,9 @@ impl LinesProcessor for DomainRemover {
 
             let reader = utils::reader_from_file(file);
 
-            // TODO: handle files with the same names but in a different dirs
             let results_path =
-                utils::build_results_path(path, &self.results_path, self.task.to_suffix());
+                self.layout
+                    .build_results_path(path, &self.results_path, self.task.to_suffix());
             let results_file = Some(utils::open_results_file(results_path)?);
 
             for (i, combo) in reader.lines().enumerate() {
@@ -88,13 +100,13 @@ impl LinesProcessor for DomainRemover {
                     }
                 };
 
-                let combo = DomainRemover::process_line(&combo);
+                let combo = self.process_line(&combo);
                 if let Some(combo) = combo {
                     results.push(combo);
                 }
 
                 if results.len() == self.save_period || lines_count - i == 1 {
-                    if let Err(e) = utils::save_results(&mut results, &results_file) {
+                    if let Err(e) = utils::save_results(&mut results, &results_file, None) {
                         eprintln!("Couldn't write to file: {}", e);
                     }
                 }
@@ -110,17 +122,3 @@ impl LinesProcessor for DomainRemover {
         Ok(())
     }
 }
-
-fn remove_domain(combo: &str) -> Option<String> {
-    let (email, password) = combo.split_once(&[':', ';'][..])?;
-    if email.is_empty() || password.is_empty() {
-        return None;
-    }
-
-    email.split('@').next().map(|username| {
-        let mut result = String::from(username);
-        result.push(':');
-        result.push_str(password);
-        result
-    })
-}
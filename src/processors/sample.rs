@@ -0,0 +1,282 @@
+use std::{
+    fs::File,
+    io::BufRead,
+    path::{Path, PathBuf},
+    time,
+};
+
+use rand::{thread_rng, Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use rustc_hash::FxHashMap;
+
+use crate::{
+    cmd::Args,
+    core::{
+        combo::ComboParser,
+        lines_processor::LinesProcessor,
+        results_layout::ResultsLayout,
+        split_key::SplitKey,
+        task::Task,
+        utils::{self, open_file_r},
+    },
+    errors::core_error::CoreError,
+};
+
+/// Random lines of the targets in one pass: an exact number of them or a share of every target
+pub struct Sampler {
+    targets: Vec<PathBuf>,
+    results_path: PathBuf,
+    save_period: usize,
+    task: Task,
+    layout: ResultsLayout,
+    size: SampleSize,
+    stratify: Option<SplitKey>,
+    seed: Option<u64>,
+    joint: bool,
+    output: Option<String>,
+    parser: ComboParser,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum SampleSize {
+    /// Reservoir sampling, the number of lines is taken from every stratum
+    Count(usize),
+    /// Bernoulli sampling, every line is taken with this probability
+    Rate(f64),
+}
+
+/// Lines kept by the reservoir of one stratum with their numbers in the input
+struct Reservoir {
+    lines: Vec<(usize, String)>,
+    seen: usize,
+}
+
+impl Reservoir {
+    fn offer(&mut self, line_num: usize, combo: String, size: usize, rng: &mut ChaCha8Rng) {
+        self.seen += 1;
+
+        if self.lines.len() < size {
+            self.lines.push((line_num, combo));
+            return;
+        }
+
+        let replaced = rng.gen_range(0..self.seen);
+        if replaced < size {
+            self.lines[replaced] = (line_num, combo);
+        }
+    }
+}
+
+impl LinesProcessor for Sampler {
+    fn new(args: Args, results_path: PathBuf, save_period: usize) -> Self {
+        let size = match (args.n, args.percent) {
+            (_, Some(percent)) => SampleSize::Rate(percent / 100.0),
+            (Some(n), None) => SampleSize::Count(n),
+            (None, None) => SampleSize::Count(utils::ask_for_number("Количество строк: ")),
+        };
+
+        Sampler {
+            targets: args.targets,
+            results_path,
+            save_period,
+            task: args.task,
+            layout: args.layout,
+            size,
+            stratify: args.stratify,
+            seed: args.seed,
+            joint: args.joint,
+            output: args.output,
+            parser: args.combo,
+        }
+    }
+
+    fn process_line(&self, _: &str) -> Option<String> {
+        unreachable!()
+    }
+
+    fn process(self) -> Result<(), CoreError> {
+        match (self.size, self.stratify) {
+            (SampleSize::Count(n), Some(key)) => println!(
+                "Обработка {} файлов. Строк: {} из каждой группы, ключ: {}",
+                self.targets.len(),
+                n,
+                key
+            ),
+            (SampleSize::Count(n), None) => {
+                println!("Обработка {} файлов. Строк: {}", self.targets.len(), n)
+            }
+            (SampleSize::Rate(rate), _) => println!(
+                "Обработка {} файлов. Доля строк: {}%",
+                self.targets.len(),
+                rate * 100.0
+            ),
+        }
+
+        let seed = self.seed.unwrap_or_else(|| thread_rng().gen());
+        println!("Сид: {}", seed);
+        let mut rng = ChaCha8Rng::seed_from_u64(seed);
+
+        let now = time::Instant::now();
+
+        if self.joint {
+            let results_path = match &self.output {
+                Some(name) => self.results_path.join(name),
+                None => self.layout.build_results_path(
+                    &self.targets[0],
+                    &self.results_path,
+                    self.task.to_suffix(),
+                ),
+            };
+            let results_file = Some(utils::open_results_file(results_path)?);
+
+            let paths: Vec<&Path> = self.targets.iter().map(PathBuf::as_path).collect();
+            let (lines_count, saved) = self.sample(&paths, &results_file, &mut rng);
+
+            println!(
+                "Строк: {}. Сохранено: {}. Потрачено: {:?}",
+                lines_count,
+                saved,
+                now.elapsed()
+            );
+
+            return Ok(());
+        }
+
+        for (file_num, path) in self.targets.iter().enumerate() {
+            let inner_now = time::Instant::now();
+
+            println!(
+                "[{}/{}]Файл: {}",
+                file_num + 1,
+                self.targets.len(),
+                path.display()
+            );
+
+            let results_path =
+                self.layout
+                    .build_results_path(path, &self.results_path, self.task.to_suffix());
+            let results_file = Some(utils::open_results_file(results_path)?);
+
+            let (lines_count, saved) = self.sample(&[path], &results_file, &mut rng);
+
+            println!(
+                "Строк: {}. Сохранено: {}. Потрачено: {:?}",
+                lines_count,
+                saved,
+                inner_now.elapsed()
+            );
+        }
+
+        if self.targets.len() > 1 {
+            println!("Потрачено в общем: {:?}", now.elapsed());
+        }
+
+        Ok(())
+    }
+}
+
+impl Sampler {
+    /// Samples lines of the files as one stream, the lines keep their order in the input.
+    /// Returns the numbers of read and written lines
+    fn sample(
+        &self,
+        paths: &[&Path],
+        results_file: &Option<File>,
+        rng: &mut ChaCha8Rng,
+    ) -> (usize, usize) {
+        // lines without the key of --stratify are a stratum of their own, so they
+        // aren't mixed with a real group of the same name
+        let mut reservoirs: FxHashMap<Option<String>, Reservoir> = FxHashMap::default();
+        let mut results: Vec<String> = Vec::with_capacity(self.save_period);
+        let mut line_num = 0usize;
+        let mut saved = 0usize;
+
+        for path in paths {
+            let file = match open_file_r(path) {
+                Ok(file) => file,
+                Err(err) => {
+                    eprintln!("Can't read input file {}. {}", path.display(), err);
+                    continue;
+                }
+            };
+
+            let reader = utils::reader_from_file(file);
+
+            for (i, combo) in reader.lines().enumerate() {
+                let combo = match combo {
+                    Ok(combo) => combo,
+                    Err(err) => {
+                        eprintln!(
+                            "Can't read combo on line {} in file {}. {}",
+                            i,
+                            path.display(),
+                            err
+                        );
+                        continue;
+                    }
+                };
+
+                match self.size {
+                    SampleSize::Count(n) => {
+                        let key = self.stratify.and_then(|key| {
+                            self.parser
+                                .parse(&combo)
+                                .and_then(|parsed| key.extract(&parsed))
+                        });
+                        let reservoir = reservoirs.entry(key).or_insert_with(|| Reservoir {
+                            lines: Vec::new(),
+                            seen: 0,
+                        });
+                        reservoir.offer(line_num, combo, n, rng);
+                    }
+                    SampleSize::Rate(rate) => {
+                        if rng.gen_bool(rate) {
+                            results.push(combo);
+                            saved += 1;
+
+                            if results.len() == self.save_period {
+                                if let Err(e) = utils::save_results(
+                                    &mut results,
+                                    results_file,
+                                    Some(&self.parser),
+                                ) {
+                                    eprintln!("Couldn't write to file: {}", e);
+                                }
+                            }
+                        }
+                    }
+                }
+
+                line_num += 1;
+            }
+        }
+
+        if self.stratify.is_some() {
+            println!("Групп: {}", reservoirs.len());
+        }
+
+        let mut sampled: Vec<(usize, String)> = reservoirs
+            .into_values()
+            .flat_map(|reservoir| reservoir.lines)
+            .collect();
+        sampled.sort_unstable_by_key(|(line_num, _)| *line_num);
+        saved += sampled.len();
+
+        for (_, combo) in sampled {
+            results.push(combo);
+
+            if results.len() == self.save_period {
+                if let Err(e) = utils::save_results(&mut results, results_file, Some(&self.parser))
+                {
+                    eprintln!("Couldn't write to file: {}", e);
+                }
+            }
+        }
+
+        if let Err(e) = utils::save_results(&mut results, results_file, Some(&self.parser)) {
+            eprintln!("Couldn't write to file: {}", e);
+        }
+
+        (line_num, saved)
+    }
+}
@@ -0,0 +1,138 @@
+use std::{fs::File, io::BufRead, path::PathBuf, time};
+
+use crate::{
+    cmd::Args,
+    core::{
+        combo::ComboParser,
+        lines_processor::LinesProcessor,
+        results_layout::ResultsLayout,
+        shard_mode::ShardMode,
+        task::Task,
+        utils::{self, open_file_r},
+    },
+    errors::core_error::CoreError,
+};
+
+pub struct Sharder {
+    targets: Vec<PathBuf>,
+    results_path: PathBuf,
+    save_period: usize,
+    task: Task,
+    layout: ResultsLayout,
+    parser: ComboParser,
+    shards_n: usize,
+    mode: ShardMode,
+}
+
+impl LinesProcessor for Sharder {
+    fn new(args: Args, results_path: PathBuf, save_period: usize) -> Self {
+        let shards_n = if let Some(n) = args.n {
+            n
+        } else {
+            utils::ask_for_number("Количество шардов: ")
+        };
+
+        Sharder {
+            targets: args.targets,
+            results_path,
+            save_period,
+            task: args.task,
+            layout: args.layout,
+            parser: args.combo,
+            shards_n,
+            mode: args.shard_by,
+        }
+    }
+
+    fn process_line(&self, _: &str) -> Option<String> {
+        unreachable!()
+    }
+
+    fn process(self) -> Result<(), CoreError> {
+        println!(
+            "Обработка {} файлов. Шардов: {}, распределение {}",
+            self.targets.len(),
+            self.shards_n,
+            self.mode
+        );
+
+        let now = time::Instant::now();
+
+        for (file_num, path) in self.targets.iter().enumerate() {
+            let inner_now = time::Instant::now();
+
+            let file = match open_file_r(path) {
+                Ok(file) => file,
+                Err(err) => {
+                    eprintln!("Can't read input file {}. {}", path.display(), err);
+                    continue;
+                }
+            };
+
+            println!(
+                "[{}/{}]Файл: {}",
+                file_num + 1,
+                self.targets.len(),
+                path.display()
+            );
+
+            let reader = utils::reader_from_file(file);
+
+            let self_results_path = self.layout.file_dir(path, &self.results_path);
+
+            // every shard is created even if no line gets into it
+            let mut shards: Vec<(Option<File>, Vec<String>)> = Vec::with_capacity(self.shards_n);
+            for num in 1..=self.shards_n {
+                let suffix = self.task.to_suffix().replace("{num}", &num.to_string());
+                let results_path = utils::build_results_path(path, &self_results_path, &suffix);
+                shards.push((
+                    Some(utils::open_results_file(results_path)?),
+                    Vec::with_capacity(self.save_period),
+                ));
+            }
+
+            let mut line_num = 0usize;
+
+            for (i, combo) in reader.lines().enumerate() {
+                let combo = match combo {
+                    Ok(combo) => combo,
+                    Err(err) => {
+                        eprintln!(
+                            "Can't read combo on line {} in file {}. {}",
+                            i,
+                            path.display(),
+                            err
+                        );
+                        continue;
+                    }
+                };
+
+                let (results_file, results) =
+                    &mut shards[self.mode.shard(&combo, line_num, self.shards_n)];
+                line_num += 1;
+
+                results.push(combo);
+
+                if results.len() == self.save_period {
+                    if let Err(e) = utils::save_results(results, results_file, Some(&self.parser)) {
+                        eprintln!("Couldn't write to file: {}", e);
+                    }
+                }
+            }
+
+            for (results_file, results) in shards.iter_mut() {
+                if let Err(e) = utils::save_results(results, results_file, Some(&self.parser)) {
+                    eprintln!("Couldn't write to file: {}", e);
+                }
+            }
+
+            println!("Строк: {}. Потрачено: {:?}", line_num, inner_now.elapsed());
+        }
+
+        if self.targets.len() > 1 {
+            println!("Потрачено в общем: {:?}", now.elapsed());
+        }
+
+        Ok(())
+    }
+}
@@ -1,24 +1,43 @@
-use std::{path::PathBuf, time};
+use std::{
+    fs::{self, File},
+    io::BufRead,
+    path::{Path, PathBuf},
+    time,
+};
 
 use memmap::MmapOptions;
 
 use crate::{
     cmd::Args,
     core::{
+        combo::ComboParser,
         lines_processor::LinesProcessor,
+        results_layout::ResultsLayout,
         task::Task,
         utils::{self, open_file_r},
     },
     errors::core_error::CoreError,
 };
 
-use rand::{prelude::SliceRandom, thread_rng};
+use rand::{prelude::SliceRandom, thread_rng, Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+
+const MEGABYTE: usize = 1024 * 1024;
+/// Lines of a bucket take about this many times more memory than on disk
+const BUCKET_OVERHEAD: usize = 4;
 
 pub struct Shuffler {
     targets: Vec<PathBuf>,
     results_path: PathBuf,
     save_period: usize,
     task: Task,
+    layout: ResultsLayout,
+    parser: ComboParser,
+    seed: Option<u64>,
+    memory_budget: usize,
+    joint: bool,
+    parts_n: usize,
+    output: Option<String>,
 }
 
 #[derive(Debug)]
@@ -34,16 +53,34 @@ impl LinesProcessor for Shuffler {
             results_path,
             save_period,
             task: args.task,
+            layout: args.layout,
+            parser: args.combo,
+            seed: args.seed,
+            memory_budget: args.memory * MEGABYTE,
+            joint: args.joint,
+            parts_n: args.n.unwrap_or(1),
+            output: args.output,
         }
     }
 
-    fn process_line(_: &str) -> Option<String> {
+    fn process_line(&self, _: &str) -> Option<String> {
         unreachable!()
     }
 
     fn process(self) -> Result<(), CoreError> {
         println!("Обработка {} файлов", self.targets.len());
 
+        // ChaCha8 gives the same sequence for the seed on every platform and rand_chacha version,
+        // so the shuffle can be replayed with the same seed, targets and --memory:
+        // the memory budget decides whether files are shuffled in memory and into how many buckets
+        let seed = self.seed.unwrap_or_else(|| thread_rng().gen());
+        println!("Сид: {}", seed);
+        let mut rng = ChaCha8Rng::seed_from_u64(seed);
+
+        if self.joint {
+            return self.shuffle_joint(&mut rng);
+        }
+
         let now = time::Instant::now();
 
         for (file_num, path) in self.targets.iter().enumerate() {
@@ -57,16 +94,171 @@ impl LinesProcessor for Shuffler {
                 }
             };
 
-            let lines_count = utils::count_lines(file);
+            let file_size = file.metadata()?.len() as usize;
 
             println!(
-                "[{}/{}]Файл: {}. Строк: {}",
+                "[{}/{}]Файл: {}",
                 file_num + 1,
                 self.targets.len(),
-                path.display(),
-                lines_count
+                path.display()
             );
 
+            let results_path =
+                self.layout
+                    .build_results_path(path, &self.results_path, self.task.to_suffix());
+            let results_file = Some(utils::open_results_file(results_path)?);
+
+            let lines_count = if file_size * BUCKET_OVERHEAD <= self.memory_budget {
+                self.shuffle_in_memory(path, &results_file, &mut rng)?
+            } else {
+                let buckets_path = self.results_path.join(format!(".shuffle_{}", file_num));
+                let buckets_n = file_size * BUCKET_OVERHEAD / self.memory_budget + 1;
+                self.shuffle_external(&[path], &buckets_path, buckets_n, &[results_file], &mut rng)?
+            };
+
+            println!(
+                "Строк: {}. Потрачено: {:?}",
+                lines_count,
+                inner_now.elapsed()
+            );
+        }
+
+        if self.targets.len() > 1 {
+            println!("Потрачено в общем: {:?}", now.elapsed());
+        }
+
+        Ok(())
+    }
+}
+
+impl Shuffler {
+    /// Shuffles the union of all targets into one file or into `parts_n` evenly sized files
+    fn shuffle_joint(&self, rng: &mut ChaCha8Rng) -> Result<(), CoreError> {
+        println!(
+            "Совместное перемешивание {} файлов в {} частей",
+            self.targets.len(),
+            self.parts_n
+        );
+
+        let now = time::Instant::now();
+
+        let mut total_size = 0usize;
+        let mut paths: Vec<&Path> = Vec::with_capacity(self.targets.len());
+        for path in &self.targets {
+            match fs::metadata(path) {
+                Ok(metadata) => {
+                    total_size += metadata.len() as usize;
+                    paths.push(path);
+                }
+                Err(err) => eprintln!("Can't read input file {}. {}", path.display(), err),
+            }
+        }
+
+        let results_path = match &self.output {
+            Some(name) => self.results_path.join(name),
+            None => self.layout.build_results_path(
+                &self.targets[0],
+                &self.results_path,
+                self.task.to_suffix(),
+            ),
+        };
+
+        let mut results_files: Vec<Option<File>> = Vec::with_capacity(self.parts_n);
+        if self.parts_n == 1 {
+            results_files.push(Some(utils::open_results_file(results_path)?));
+        } else {
+            for num in 1..=self.parts_n {
+                let part_path = utils::build_results_path(
+                    results_path.as_path(),
+                    &self.results_path,
+                    &format!("_{}", num),
+                );
+                results_files.push(Some(utils::open_results_file(part_path)?));
+            }
+        }
+
+        // lines of different files have to get into the same buckets to be mixed,
+        // so even small targets are shuffled on disk
+        let buckets_path = self.results_path.join(".shuffle");
+        let buckets_n = total_size * BUCKET_OVERHEAD / self.memory_budget + 1;
+        let lines_count =
+            self.shuffle_external(&paths, &buckets_path, buckets_n, &results_files, rng)?;
+
+        println!("Строк: {}. Потрачено: {:?}", lines_count, now.elapsed());
+
+        Ok(())
+    }
+
+    /// Shuffles offsets of the lines in the mapped file, returns the number of lines
+    fn shuffle_in_memory(
+        &self,
+        path: &Path,
+        results_file: &Option<File>,
+        rng: &mut ChaCha8Rng,
+    ) -> Result<usize, CoreError> {
+        let file = open_file_r(path)?;
+        let mmap = unsafe { MmapOptions::new().map(&file)? };
+
+        let mut start = 0usize;
+
+        let mut lines_offsets: Vec<ComboOffset> = Vec::new();
+
+        println!("Поиск сдвигов...");
+
+        for (end, &char) in mmap.iter().enumerate() {
+            if char == b'\n' || mmap.len() - end == 1 {
+                lines_offsets.push(ComboOffset { start, end });
+                start = end + 1;
+            }
+        }
+
+        println!("Перемешивание...");
+
+        lines_offsets.shuffle(rng);
+
+        let lines_count = lines_offsets.len();
+        let mut results: Vec<String> = Vec::with_capacity(self.save_period);
+
+        println!("Сохранение результатов...");
+
+        for (i, offset) in lines_offsets.iter().enumerate() {
+            let data = &mmap[offset.start..=offset.end];
+            results.push(utils::decode_line(data));
+
+            if results.len() == self.save_period || lines_count - i == 1 {
+                if let Err(e) = utils::save_results(&mut results, results_file, Some(&self.parser))
+                {
+                    eprintln!("Couldn't write to file: {}", e);
+                }
+            }
+        }
+
+        Ok(lines_count)
+    }
+
+    /// Reads the files sequentially scattering lines into random buckets on disk,
+    /// then shuffles every bucket in memory. The shuffled lines are divided evenly
+    /// between the results files in turn. Returns the number of lines
+    fn shuffle_external(
+        &self,
+        paths: &[&Path],
+        buckets_path: &Path,
+        buckets_n: usize,
+        results_files: &[Option<File>],
+        rng: &mut ChaCha8Rng,
+    ) -> Result<usize, CoreError> {
+        println!("Раскладывание строк в {} частей...", buckets_n);
+
+        let mut buckets: Vec<(PathBuf, Option<File>, Vec<String>)> = Vec::with_capacity(buckets_n);
+        for bucket_num in 0..buckets_n {
+            let bucket_path = buckets_path.join(format!("{}.txt", bucket_num));
+            let bucket_file = Some(utils::open_results_file(&bucket_path)?);
+            buckets.push((bucket_path, bucket_file, Vec::new()));
+        }
+
+        let mut lines_count = 0usize;
+
+        for path in paths {
             let file = match open_file_r(path) {
                 Ok(file) => file,
                 Err(err) => {
@@ -75,56 +267,135 @@ impl LinesProcessor for Shuffler {
                 }
             };
 
-            // TODO: handle files with the same names but in a different dirs
-            let results_path =
-                utils::build_results_path(path, &self.results_path, self.task.to_suffix());
-            let results_file = Some(utils::open_results_file(results_path)?);
-
-            let mmap = unsafe { MmapOptions::new().map(&file)? };
+            let reader = utils::reader_from_file(file);
 
-            let mut start = 0usize;
-
-            let mut lines_offsets: Vec<ComboOffset> = Vec::with_capacity(lines_count);
+            for (i, combo) in reader.lines().enumerate() {
+                let combo = match combo {
+                    Ok(combo) => combo,
+                    Err(err) => {
+                        eprintln!(
+                            "Can't read combo on line {} in file {}. {}",
+                            i,
+                            path.display(),
+                            err
+                        );
+                        continue;
+                    }
+                };
 
-            println!("Поиск сдвигов...");
+                let (_, bucket_file, results) = &mut buckets[rng.gen_range(0..buckets_n)];
+                results.push(combo);
+                lines_count += 1;
 
-            for (end, &char) in mmap.iter().enumerate() {
-                if char == b'\n' || mmap.len() - end == 1 {
-                    lines_offsets.push(ComboOffset { start, end });
-                    start = end + 1;
+                if results.len() == self.save_period {
+                    if let Err(e) = utils::save_results(results, bucket_file, None) {
+                        eprintln!("Couldn't write to file: {}", e);
+                    }
                 }
             }
+        }
 
-            let mut rng = thread_rng();
-
-            println!("Перемешивание...");
-
-            lines_offsets.shuffle(&mut rng);
+        for (_, bucket_file, results) in buckets.iter_mut() {
+            if let Err(e) = utils::save_results(results, bucket_file, None) {
+                eprintln!("Couldn't write to file: {}", e);
+            }
+            // the bucket is read back, so its file has to be closed
+            *bucket_file = None;
+        }
 
-            let mut results: Vec<String> = Vec::with_capacity(self.save_period);
+        println!("Перемешивание частей...");
 
-            println!("Сохранение результатов...");
+        let mut written = 0usize;
 
-            for (i, offset) in lines_offsets.iter().enumerate() {
-                let data = &mmap[offset.start..=offset.end];
-                let combo = String::from_utf8_lossy(data).trim_end().to_owned();
+        for (bucket_path, _, _) in buckets {
+            let mut lines = utils::read_decoded_lines(&bucket_path)?;
+            lines.shuffle(rng);
 
-                results.push(combo);
+            let mut results: Vec<Vec<String>> = vec![Vec::new(); results_files.len()];
+            for combo in lines {
+                // consecutive ranges of the shuffled lines, sizes differ by one line at most
+                let part = written * results_files.len() / lines_count;
+                written += 1;
 
-                if results.len() == self.save_period || lines_count - i == 1 {
-                    if let Err(e) = utils::save_results(&mut results, &results_file) {
+                results[part].push(combo);
+                if results[part].len() == self.save_period {
+                    if let Err(e) = utils::save_results(
+                        &mut results[part],
+                        &results_files[part],
+                        Some(&self.parser),
+                    ) {
                         eprintln!("Couldn't write to file: {}", e);
                     }
                 }
             }
 
-            println!("Потрачено: {:?}", inner_now.elapsed());
+            for (results, results_file) in results.iter_mut().zip(results_files) {
+                if let Err(e) = utils::save_results(results, results_file, Some(&self.parser)) {
+                    eprintln!("Couldn't write to file: {}", e);
+                }
+            }
+
+            if let Err(err) = fs::remove_file(&bucket_path) {
+                eprintln!("Can't remove file {}. {}", bucket_path.display(), err);
+            }
         }
 
-        if self.targets.len() > 1 {
-            println!("Потрачено в общем: {:?}", now.elapsed());
+        if let Err(err) = fs::remove_dir(buckets_path) {
+            eprintln!("Can't remove directory {}. {}", buckets_path.display(), err);
         }
 
-        Ok(())
+        Ok(lines_count)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{ffi::OsString, fs};
+
+    use super::Shuffler;
+    use crate::{
+        cmd,
+        core::{lines_processor::LinesProcessor, utils},
+    };
+
+    #[test]
+    fn buckets_keep_lines_as_they_are() {
+        let dir = utils::test_dir("shuffle_buckets");
+        let target = dir.join("combos.txt");
+        fs::write(
+            &target,
+            b"a@b.c:pass  \nd@e.f:pass\t\r\ng@h.i:\xe9t\xe9\n\n",
+        )
+        .unwrap();
+        let results_path = dir.join("results");
+
+        // joint shuffle always goes through the buckets on disk
+        let args = [
+            "combosutils",
+            "--task",
+            "shuffle",
+            "--joint",
+            "--seed",
+            "41",
+            "--target",
+        ]
+        .iter()
+        .map(OsString::from)
+        .chain([target.into_os_string()]);
+        Shuffler::new(cmd::parse_args_from(args).unwrap(), results_path.clone(), 2)
+            .process()
+            .unwrap();
+
+        let results = utils::test_results(&results_path);
+        assert_eq!(results.len(), 1);
+
+        let mut lines = utils::read_decoded_lines(&results[0]).unwrap();
+        lines.sort();
+        assert_eq!(
+            lines,
+            ["", "a@b.c:pass  ", "d@e.f:pass\t", "g@h.i:\u{e9}t\u{e9}"]
+        );
+
+        fs::remove_dir_all(&dir).unwrap();
     }
 }
@@ -0,0 +1,240 @@
+use std::{
+    fs::File,
+    io::BufRead,
+    path::{Path, PathBuf},
+    time,
+};
+
+use memmap::{Mmap, MmapOptions};
+
+use crate::{
+    cmd::Args,
+    core::{
+        combo::ComboParser,
+        lines_processor::LinesProcessor,
+        results_layout::ResultsLayout,
+        slice::Slice,
+        task::Task,
+        utils::{self, open_file_r},
+    },
+    errors::core_error::CoreError,
+};
+
+/// Cuts lines out of the targets. Lines from the start are read until the slice ends,
+/// tail and bytes ranges are found in the mapped file without reading the lines before them
+pub struct Slicer {
+    targets: Vec<PathBuf>,
+    results_path: PathBuf,
+    save_period: usize,
+    task: Task,
+    layout: ResultsLayout,
+    parser: ComboParser,
+    slice: Slice,
+}
+
+impl LinesProcessor for Slicer {
+    fn new(args: Args, results_path: PathBuf, save_period: usize) -> Self {
+        Slicer {
+            targets: args.targets,
+            results_path,
+            save_period,
+            task: args.task,
+            layout: args.layout,
+            parser: args.combo,
+            slice: args.slice.unwrap(),
+        }
+    }
+
+    fn process_line(&self, _: &str) -> Option<String> {
+        unreachable!()
+    }
+
+    fn process(self) -> Result<(), CoreError> {
+        println!(
+            "Обработка {} файлов. Вырезать {}",
+            self.targets.len(),
+            self.slice
+        );
+
+        let now = time::Instant::now();
+
+        for (file_num, path) in self.targets.iter().enumerate() {
+            let inner_now = time::Instant::now();
+
+            let file = match open_file_r(path) {
+                Ok(file) => file,
+                Err(err) => {
+                    eprintln!("Can't read input file {}. {}", path.display(), err);
+                    continue;
+                }
+            };
+
+            println!(
+                "[{}/{}]Файл: {}",
+                file_num + 1,
+                self.targets.len(),
+                path.display()
+            );
+
+            let results_path =
+                self.layout
+                    .build_results_path(path, &self.results_path, self.task.to_suffix());
+            let results_file = Some(utils::open_results_file(results_path)?);
+
+            // empty files can't be mapped
+            let is_empty = file.metadata()?.len() == 0;
+
+            let lines_count = match self.slice {
+                Slice::Tail(_) | Slice::Bytes { .. } if is_empty => 0,
+                Slice::Head(n) => self.save_lines(file, path, 1, Some(n), &results_file),
+                Slice::Lines { from, to } => self.save_lines(file, path, from, to, &results_file),
+                Slice::Tail(n) => {
+                    let mmap = unsafe { MmapOptions::new().map(&file)? };
+                    let start = tail_start(&mmap, n);
+                    self.save_mapped(&mmap[start..], &results_file)
+                }
+                Slice::Bytes { from, to } => {
+                    let mmap = unsafe { MmapOptions::new().map(&file)? };
+                    let start = line_start(&mmap, from);
+                    let end = match to {
+                        Some(to) => line_start(&mmap, to),
+                        None => mmap.len(),
+                    };
+                    self.save_mapped(&mmap[start..end.max(start)], &results_file)
+                }
+            };
+
+            println!(
+                "Строк: {}. Потрачено: {:?}",
+                lines_count,
+                inner_now.elapsed()
+            );
+        }
+
+        if self.targets.len() > 1 {
+            println!("Потрачено в общем: {:?}", now.elapsed());
+        }
+
+        Ok(())
+    }
+}
+
+impl Slicer {
+    /// Writes lines from `from` to `to` and stops reading after them, returns the number of written lines
+    fn save_lines(
+        &self,
+        file: File,
+        path: &Path,
+        from: usize,
+        to: Option<usize>,
+        results_file: &Option<File>,
+    ) -> usize {
+        let reader = utils::reader_from_file(file);
+
+        let mut results: Vec<String> = Vec::with_capacity(self.save_period);
+        let mut lines_count = 0usize;
+
+        for (i, combo) in reader.lines().enumerate() {
+            let line_num = i + 1;
+            if line_num < from {
+                continue;
+            }
+            if let Some(to) = to {
+                if line_num > to {
+                    break;
+                }
+            }
+
+            let combo = match combo {
+                Ok(combo) => combo,
+                Err(err) => {
+                    eprintln!(
+                        "Can't read combo on line {} in file {}. {}",
+                        i,
+                        path.display(),
+                        err
+                    );
+                    continue;
+                }
+            };
+
+            results.push(combo);
+            lines_count += 1;
+
+            if results.len() == self.save_period {
+                if let Err(e) = utils::save_results(&mut results, results_file, Some(&self.parser))
+                {
+                    eprintln!("Couldn't write to file: {}", e);
+                }
+            }
+        }
+
+        if let Err(e) = utils::save_results(&mut results, results_file, Some(&self.parser)) {
+            eprintln!("Couldn't write to file: {}", e);
+        }
+
+        lines_count
+    }
+
+    /// Writes lines of the mapped part of the file, returns the number of written lines
+    fn save_mapped(&self, data: &[u8], results_file: &Option<File>) -> usize {
+        // the last line may have no newline
+        let data = data.strip_suffix(b"\n").unwrap_or(data);
+        if data.is_empty() {
+            return 0;
+        }
+
+        let mut results: Vec<String> = Vec::with_capacity(self.save_period);
+        let mut lines_count = 0usize;
+
+        for line in data.split(|&char| char == b'\n') {
+            results.push(utils::decode_line(line));
+            lines_count += 1;
+
+            if results.len() == self.save_period {
+                if let Err(e) = utils::save_results(&mut results, results_file, Some(&self.parser))
+                {
+                    eprintln!("Couldn't write to file: {}", e);
+                }
+            }
+        }
+
+        if let Err(e) = utils::save_results(&mut results, results_file, Some(&self.parser)) {
+            eprintln!("Couldn't write to file: {}", e);
+        }
+
+        lines_count
+    }
+}
+
+/// Start of the first line which begins at `offset` or after it
+fn line_start(mmap: &Mmap, offset: usize) -> usize {
+    if offset == 0 {
+        return 0;
+    }
+    if offset >= mmap.len() {
+        return mmap.len();
+    }
+
+    match mmap[offset - 1..].iter().position(|&char| char == b'\n') {
+        Some(newline) => offset + newline,
+        None => mmap.len(),
+    }
+}
+
+/// Start of the `n`-th line from the end
+fn tail_start(mmap: &Mmap, n: usize) -> usize {
+    let data = mmap.strip_suffix(b"\n").unwrap_or(mmap);
+
+    let mut lines = 0usize;
+    for (pos, &char) in data.iter().enumerate().rev() {
+        if char == b'\n' {
+            lines += 1;
+            if lines == n {
+                return pos + 1;
+            }
+        }
+    }
+
+    0
+}
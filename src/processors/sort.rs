@@ -0,0 +1,315 @@
+use std::{
+    cmp::Reverse,
+    collections::BinaryHeap,
+    fs::{self, File},
+    io::BufRead,
+    path::{Path, PathBuf},
+    time,
+};
+
+use rayon::prelude::*;
+
+use crate::{
+    cmd::Args,
+    core::{
+        combo::ComboParser,
+        lines_processor::LinesProcessor,
+        results_layout::ResultsLayout,
+        sort_key::{SortEntry, SortOptions},
+        task::Task,
+        utils::{self, open_file_r},
+    },
+    errors::core_error::CoreError,
+};
+
+const MEGABYTE: usize = 1024 * 1024;
+
+/// External merge sort: sorted runs which fit into the memory budget are written to disk and merged
+pub struct ExternalSorter {
+    targets: Vec<PathBuf>,
+    results_path: PathBuf,
+    save_period: usize,
+    task: Task,
+    layout: ResultsLayout,
+    parser: ComboParser,
+    options: SortOptions,
+    unique: bool,
+    memory_budget: usize,
+}
+
+impl LinesProcessor for ExternalSorter {
+    fn new(args: Args, results_path: PathBuf, save_period: usize) -> Self {
+        ExternalSorter {
+            targets: args.targets,
+            results_path,
+            save_period,
+            task: args.task,
+            layout: args.layout,
+            parser: args.combo,
+            options: args.sort,
+            unique: args.unique,
+            memory_budget: args.memory * MEGABYTE,
+        }
+    }
+
+    fn process_line(&self, _: &str) -> Option<String> {
+        unreachable!()
+    }
+
+    fn process(self) -> Result<(), CoreError> {
+        match &self.options.field {
+            Some(field) => println!(
+                "Обработка {} файлов. Ключ: поле {}",
+                self.targets.len(),
+                field
+            ),
+            None => println!(
+                "Обработка {} файлов. Ключ: {}",
+                self.targets.len(),
+                self.options.key
+            ),
+        }
+
+        let now = time::Instant::now();
+
+        for (file_num, path) in self.targets.iter().enumerate() {
+            let inner_now = time::Instant::now();
+
+            let file = match open_file_r(path) {
+                Ok(file) => file,
+                Err(err) => {
+                    eprintln!("Can't read input file {}. {}", path.display(), err);
+                    continue;
+                }
+            };
+
+            println!(
+                "[{}/{}]Файл: {}",
+                file_num + 1,
+                self.targets.len(),
+                path.display()
+            );
+
+            let reader = utils::reader_from_file(file);
+
+            let results_path =
+                self.layout
+                    .build_results_path(path, &self.results_path, self.task.to_suffix());
+            let results_file = Some(utils::open_results_file(results_path)?);
+
+            let runs_path = self.results_path.join(format!(".sort_{}", file_num));
+            let mut runs: Vec<PathBuf> = Vec::new();
+
+            let mut chunk: Vec<SortEntry> = Vec::new();
+            let mut chunk_size = 0usize;
+            let mut lines_count = 0usize;
+
+            println!("Сортировка частей...");
+
+            for (i, combo) in reader.lines().enumerate() {
+                let combo = match combo {
+                    Ok(combo) => combo,
+                    Err(err) => {
+                        eprintln!(
+                            "Can't read combo on line {} in file {}. {}",
+                            i,
+                            path.display(),
+                            err
+                        );
+                        continue;
+                    }
+                };
+
+                let entry = self.options.entry(combo);
+                chunk_size += entry.size();
+                chunk.push(entry);
+                lines_count += 1;
+
+                if chunk_size >= self.memory_budget {
+                    let run_path = runs_path.join(format!("{}.txt", runs.len()));
+                    let run_file = Some(utils::open_results_file(&run_path)?);
+                    self.save_sorted(&mut chunk, &run_file, None);
+                    runs.push(run_path);
+                    chunk_size = 0;
+                }
+            }
+
+            let saved = if runs.is_empty() {
+                self.save_sorted(&mut chunk, &results_file, Some(&self.parser))
+            } else {
+                if !chunk.is_empty() {
+                    let run_path = runs_path.join(format!("{}.txt", runs.len()));
+                    let run_file = Some(utils::open_results_file(&run_path)?);
+                    self.save_sorted(&mut chunk, &run_file, None);
+                    runs.push(run_path);
+                }
+                drop(chunk);
+
+                println!("Слияние {} частей...", runs.len());
+
+                let saved = self.merge_runs(&runs, &results_file)?;
+                if let Err(err) = fs::remove_dir_all(&runs_path) {
+                    eprintln!("Can't remove directory {}. {}", runs_path.display(), err);
+                }
+                saved
+            };
+
+            println!(
+                "Строк: {}. Сохранено: {}. Потрачено: {:?}",
+                lines_count,
+                saved,
+                inner_now.elapsed()
+            );
+        }
+
+        if self.targets.len() > 1 {
+            println!("Потрачено в общем: {:?}", now.elapsed());
+        }
+
+        Ok(())
+    }
+}
+
+impl ExternalSorter {
+    /// Sorts the chunk and writes it, returns the number of written lines.
+    /// Runs are written without `output`, they're formatted when merged
+    fn save_sorted(
+        &self,
+        chunk: &mut Vec<SortEntry>,
+        results_file: &Option<File>,
+        output: Option<&ComboParser>,
+    ) -> usize {
+        chunk.par_sort_unstable();
+        if self.unique {
+            chunk.dedup_by(|a, b| a.same_key(b));
+        }
+
+        let saved = chunk.len();
+        for part in chunk.chunks(self.save_period) {
+            let mut results: Vec<&str> = part.iter().map(|entry| entry.line.as_str()).collect();
+            if let Err(e) = utils::save_results(&mut results, results_file, output) {
+                eprintln!("Couldn't write to file: {}", e);
+            }
+        }
+        chunk.clear();
+
+        saved
+    }
+
+    /// K-way merge of sorted runs, returns the number of written lines
+    fn merge_runs(
+        &self,
+        runs: &[PathBuf],
+        results_file: &Option<File>,
+    ) -> Result<usize, CoreError> {
+        let mut readers = Vec::with_capacity(runs.len());
+        for run in runs {
+            readers.push(utils::reader_from_file(open_file_r(run)?).lines());
+        }
+
+        let mut heap = BinaryHeap::with_capacity(runs.len());
+        for (run_num, reader) in readers.iter_mut().enumerate() {
+            if let Some(entry) = self.next_entry(reader, &runs[run_num]) {
+                heap.push(Reverse((entry, run_num)));
+            }
+        }
+
+        let mut results: Vec<String> = Vec::with_capacity(self.save_period);
+        let mut last: Option<SortEntry> = None;
+        let mut saved = 0usize;
+
+        while let Some(Reverse((entry, run_num))) = heap.pop() {
+            if let Some(next) = self.next_entry(&mut readers[run_num], &runs[run_num]) {
+                heap.push(Reverse((next, run_num)));
+            }
+
+            if self.unique {
+                if let Some(last) = &last {
+                    if last.same_key(&entry) {
+                        continue;
+                    }
+                }
+            }
+
+            results.push(entry.line.clone());
+            saved += 1;
+            last = Some(entry);
+
+            if results.len() == self.save_period {
+                if let Err(e) = utils::save_results(&mut results, results_file, Some(&self.parser))
+                {
+                    eprintln!("Couldn't write to file: {}", e);
+                }
+            }
+        }
+
+        if !results.is_empty() {
+            if let Err(e) = utils::save_results(&mut results, results_file, Some(&self.parser)) {
+                eprintln!("Couldn't write to file: {}", e);
+            }
+        }
+
+        Ok(saved)
+    }
+
+    fn next_entry(
+        &self,
+        reader: &mut impl Iterator<Item = std::io::Result<String>>,
+        run: &Path,
+    ) -> Option<SortEntry> {
+        for combo in reader.by_ref() {
+            match combo {
+                Ok(combo) => return Some(self.options.entry(combo)),
+                Err(err) => eprintln!("Can't read combo in file {}. {}", run.display(), err),
+            }
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{ffi::OsString, fs};
+
+    use super::ExternalSorter;
+    use crate::{
+        cmd,
+        core::{lines_processor::LinesProcessor, utils},
+    };
+
+    #[test]
+    fn columns_are_written_once_after_runs() {
+        let dir = utils::test_dir("sort_columns");
+        let target = dir.join("combos.txt");
+        fs::write(&target, "b@x.com:2:ip2\nc@x.com:3:ip3\na@x.com:1:ip1\n").unwrap();
+        let results_path = dir.join("results");
+
+        let args = [
+            "combosutils",
+            "--task",
+            "sort",
+            "--schema",
+            "email,password,ip",
+            "--columns",
+            "ip,email",
+            "--target",
+        ]
+        .iter()
+        .map(OsString::from)
+        .chain([target.into_os_string()]);
+        let mut sorter =
+            ExternalSorter::new(cmd::parse_args_from(args).unwrap(), results_path.clone(), 2);
+        // every line is a run of its own
+        sorter.memory_budget = 1;
+        sorter.process().unwrap();
+
+        let results = utils::test_results(&results_path);
+        assert_eq!(results.len(), 1);
+        assert_eq!(
+            utils::read_decoded_lines(&results[0]).unwrap(),
+            ["ip1:a@x.com", "ip2:b@x.com", "ip3:c@x.com"]
+        );
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,332 @@
+use std::{
+    collections::HashMap,
+    fs::File,
+    io::BufRead,
+    path::{Path, PathBuf},
+    time,
+};
+
+use rustc_hash::{FxHashMap, FxHashSet};
+
+use crate::{
+    cmd::Args,
+    core::{
+        combo::ComboParser,
+        lines_processor::LinesProcessor,
+        results_layout::ResultsLayout,
+        split_key::SplitKey,
+        task::Task,
+        utils::{self, open_file_r},
+    },
+    errors::core_error::CoreError,
+};
+
+/// File name of the lines without a key or from small groups, keys get other names
+const OTHER_KEY: &str = "other";
+/// All buffers are written when they hold more lines than this
+const BUFFERED_LINES_LIMIT: usize = 1_000_000;
+
+pub struct ByKeySplitter {
+    targets: Vec<PathBuf>,
+    results_path: PathBuf,
+    save_period: usize,
+    task: Task,
+    layout: ResultsLayout,
+    key: SplitKey,
+    max_open: usize,
+    min_group: usize,
+    parser: ComboParser,
+    field: Option<String>,
+}
+
+/// Output file of one key, the file is closed when too many files are open
+struct KeyOutput {
+    path: PathBuf,
+    file: Option<File>,
+    created: bool,
+    results: Vec<String>,
+    last_used: usize,
+    saved: usize,
+}
+
+impl LinesProcessor for ByKeySplitter {
+    fn new(args: Args, results_path: PathBuf, save_period: usize) -> Self {
+        ByKeySplitter {
+            targets: args.targets,
+            results_path,
+            save_period,
+            task: args.task,
+            layout: args.layout,
+            key: args.split_key,
+            max_open: args.max_open,
+            min_group: args.min_group,
+            parser: args.combo,
+            field: args.sort.field,
+        }
+    }
+
+    fn process_line(&self, _: &str) -> Option<String> {
+        unreachable!()
+    }
+
+    fn process(self) -> Result<(), CoreError> {
+        match &self.field {
+            Some(field) => println!(
+                "Обработка {} файлов. Ключ: поле {}",
+                self.targets.len(),
+                field
+            ),
+            None => println!(
+                "Обработка {} файлов. Ключ: {}",
+                self.targets.len(),
+                self.key
+            ),
+        }
+
+        let now = time::Instant::now();
+
+        for (file_num, path) in self.targets.iter().enumerate() {
+            let inner_now = time::Instant::now();
+
+            println!(
+                "[{}/{}]Файл: {}",
+                file_num + 1,
+                self.targets.len(),
+                path.display()
+            );
+
+            // groups are known only after the whole file is read, so small ones need a separate pass
+            let groups = if self.min_group > 1 {
+                println!("Подсчёт размеров групп...");
+                match self.count_groups(path) {
+                    Ok(groups) => Some(groups),
+                    Err(err) => {
+                        eprintln!("Can't read input file {}. {}", path.display(), err);
+                        continue;
+                    }
+                }
+            } else {
+                None
+            };
+
+            let file = match open_file_r(path) {
+                Ok(file) => file,
+                Err(err) => {
+                    eprintln!("Can't read input file {}. {}", path.display(), err);
+                    continue;
+                }
+            };
+
+            let reader = utils::reader_from_file(file);
+
+            let self_results_path = self.layout.file_dir(path, &self.results_path);
+
+            // `None` is the group of the other lines
+            let mut outputs: FxHashMap<Option<String>, KeyOutput> = FxHashMap::default();
+            // file names of the outputs in lowercase, they must differ on case-insensitive file systems
+            let mut file_names: FxHashSet<String> = FxHashSet::default();
+            file_names.insert(OTHER_KEY.to_owned());
+            let mut open_files = 0usize;
+            let mut buffered = 0usize;
+
+            for (i, combo) in reader.lines().enumerate() {
+                let combo = match combo {
+                    Ok(combo) => combo,
+                    Err(err) => {
+                        eprintln!(
+                            "Can't read combo on line {} in file {}. {}",
+                            i,
+                            path.display(),
+                            err
+                        );
+                        continue;
+                    }
+                };
+
+                let key = match (self.extract_key(&combo), &groups) {
+                    (Some(key), Some(groups))
+                        if groups.get(&key).copied().unwrap_or(0) < self.min_group =>
+                    {
+                        None
+                    }
+                    (key, _) => key,
+                };
+
+                if !outputs.contains_key(&key) {
+                    let file_name = match &key {
+                        Some(key) => unique_file_name(&mut file_names, key),
+                        None => OTHER_KEY.to_owned(),
+                    };
+                    let suffix = self.task.to_suffix().replace("{key}", &file_name);
+                    let output = KeyOutput {
+                        path: utils::build_results_path(path, &self_results_path, &suffix),
+                        file: None,
+                        created: false,
+                        results: Vec::new(),
+                        last_used: 0,
+                        saved: 0,
+                    };
+                    outputs.insert(key.clone(), output);
+                }
+
+                let output = outputs.get_mut(&key).unwrap();
+                output.results.push(combo);
+                output.last_used = i;
+                buffered += 1;
+
+                if output.results.len() == self.save_period {
+                    buffered -= output.results.len();
+                    self.save_output(&mut outputs, &key, &mut open_files)?;
+                }
+
+                if buffered > BUFFERED_LINES_LIMIT {
+                    self.save_all(&mut outputs, &mut open_files)?;
+                    buffered = 0;
+                }
+            }
+
+            self.save_all(&mut outputs, &mut open_files)?;
+
+            let mut counts: Vec<(&Option<String>, usize)> = outputs
+                .iter()
+                .map(|(key, output)| (key, output.saved))
+                .collect();
+            counts.sort_unstable_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(b.0)));
+            for (key, count) in counts.iter().take(10) {
+                println!("{}: {}", key.as_deref().unwrap_or("<остальные>"), count);
+            }
+
+            println!(
+                "Файлов: {}. Потрачено: {:?}",
+                outputs.len(),
+                inner_now.elapsed()
+            );
+        }
+
+        if self.targets.len() > 1 {
+            println!("Потрачено в общем: {:?}", now.elapsed());
+        }
+
+        Ok(())
+    }
+}
+
+impl ByKeySplitter {
+    fn extract_key(&self, line: &str) -> Option<String> {
+        let combo = self.parser.parse(line)?;
+        match &self.field {
+            Some(field) => self
+                .parser
+                .field(&combo, field)
+                .filter(|value| !value.is_empty())
+                .map(str::to_owned),
+            None => self.key.extract(&combo),
+        }
+    }
+
+    fn count_groups(&self, path: &Path) -> std::io::Result<HashMap<String, usize>> {
+        let file = open_file_r(path)?;
+        let reader = utils::reader_from_file(file);
+
+        let mut groups: HashMap<String, usize> = HashMap::new();
+        for combo in reader.lines() {
+            let combo = if let Ok(combo) = combo {
+                combo
+            } else {
+                continue;
+            };
+
+            if let Some(key) = self.extract_key(&combo) {
+                *groups.entry(key).or_insert(0) += 1;
+            }
+        }
+
+        Ok(groups)
+    }
+
+    /// Writes buffered lines of the `key` output, the least recently used file is closed
+    /// when there are `max_open` files open already
+    fn save_output(
+        &self,
+        outputs: &mut FxHashMap<Option<String>, KeyOutput>,
+        key: &Option<String>,
+        open_files: &mut usize,
+    ) -> Result<(), CoreError> {
+        let is_open = matches!(outputs.get(key), Some(KeyOutput { file: Some(_), .. }));
+
+        if !is_open && *open_files >= self.max_open {
+            let least_used = outputs
+                .values_mut()
+                .filter(|output| output.file.is_some())
+                .min_by_key(|output| output.last_used);
+            if let Some(output) = least_used {
+                output.file = None;
+                *open_files -= 1;
+            }
+        }
+
+        let output = match outputs.get_mut(key) {
+            Some(output) => output,
+            None => return Ok(()),
+        };
+
+        if output.file.is_none() {
+            output.file = Some(if output.created {
+                utils::reopen_results_file(&output.path)?
+            } else {
+                utils::open_results_file(&output.path)?
+            });
+            output.created = true;
+            *open_files += 1;
+        }
+
+        output.saved += output.results.len();
+        if let Err(e) = utils::save_results(&mut output.results, &output.file, Some(&self.parser)) {
+            eprintln!("Couldn't write to file: {}", e);
+        }
+
+        Ok(())
+    }
+
+    fn save_all(
+        &self,
+        outputs: &mut FxHashMap<Option<String>, KeyOutput>,
+        open_files: &mut usize,
+    ) -> Result<(), CoreError> {
+        let pending: Vec<Option<String>> = outputs
+            .iter()
+            .filter(|(_, output)| !output.results.is_empty())
+            .map(|(key, _)| key.clone())
+            .collect();
+
+        for key in pending {
+            self.save_output(outputs, &key, open_files)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// File name of the key which differs from the `taken` ones regardless of the case,
+/// a counter is added to it on collision
+fn unique_file_name(taken: &mut FxHashSet<String>, key: &str) -> String {
+    let name = file_name_part(key);
+    let mut unique = name.clone();
+    let mut n = 1;
+    while !taken.insert(unique.to_lowercase()) {
+        n += 1;
+        unique = format!("{}_{}", name, n);
+    }
+    unique
+}
+
+/// Key with characters which can't be in Windows file names replaced
+fn file_name_part(key: &str) -> String {
+    key.chars()
+        .map(|c| match c {
+            '<' | '>' | ':' | '"' | '/' | '\\' | '|' | '?' | '*' => '_',
+            c if c.is_control() => '_',
+            c => c,
+        })
+        .collect()
+}
@@ -3,7 +3,9 @@ use std::{io::BufRead, path::PathBuf, time};
 use crate::{
     cmd::Args,
     core::{
+        combo::ComboParser,
         lines_processor::LinesProcessor,
+        results_layout::ResultsLayout,
         task::Task,
         utils::{self, open_file_r},
     },
@@ -15,6 +17,8 @@ pub struct ByLinesSplitter {
     results_path: PathBuf,
     save_period: usize,
     task: Task,
+    layout: ResultsLayout,
+    parser: ComboParser,
     lines_n: usize,
 }
 
@@ -37,11 +41,13 @@ impl LinesProcessor for ByLinesSplitter {
             results_path,
             save_period,
             task: args.task,
+            layout: args.layout,
+            parser: args.combo,
             lines_n,
         }
     }
 
-    fn process_line(_: &str) -> Option<String> {
+    fn process_line(&self, _: &str) -> Option<String> {
         unreachable!()
     }
 
@@ -89,10 +95,8 @@ impl LinesProcessor for ByLinesSplitter {
 
             let reader = utils::reader_from_file(file);
 
-            // TODO: handle files with the same names but in a different dirs
             let suffix = self.task.to_suffix().replace("{num}", &lines_n.to_string());
-            let mut self_results_path = self.results_path.clone();
-            self_results_path.push(path.file_name().unwrap_or_default());
+            let self_results_path = self.layout.file_dir(path, &self.results_path);
 
             let mut results_path = utils::build_results_path(path, &self_results_path, &suffix);
             let mut results_file = Some(utils::open_results_file(results_path)?);
@@ -128,7 +132,9 @@ impl LinesProcessor for ByLinesSplitter {
                     && lines_n - already_written >= self.save_period;
 
                 if need_save || last_combo {
-                    if let Err(e) = utils::save_results(&mut results, &results_file) {
+                    if let Err(e) =
+                        utils::save_results(&mut results, &results_file, Some(&self.parser))
+                    {
                         eprintln!("Couldn't write to file: {}", e);
                     }
                     already_written += self.save_period;
@@ -137,7 +143,9 @@ impl LinesProcessor for ByLinesSplitter {
 
                     let mut to_be_written: Vec<&String> = results.iter().take(need_write).collect();
 
-                    if let Err(e) = utils::save_results(&mut to_be_written, &results_file) {
+                    if let Err(e) =
+                        utils::save_results(&mut to_be_written, &results_file, Some(&self.parser))
+                    {
                         eprintln!("Couldn't write to file: {}", e);
                     }
 
@@ -3,7 +3,9 @@ use std::{io::BufRead, path::PathBuf, time};
 use crate::{
     cmd::Args,
     core::{
+        combo::ComboParser,
         lines_processor::LinesProcessor,
+        results_layout::ResultsLayout,
         task::Task,
         utils::{self, open_file_r},
     },
@@ -15,6 +17,8 @@ pub struct ByPartsSplitter {
     results_path: PathBuf,
     save_period: usize,
     task: Task,
+    layout: ResultsLayout,
+    parser: ComboParser,
     parts_n: usize,
 }
 
@@ -31,15 +35,17 @@ impl LinesProcessor for ByPartsSplitter {
             results_path,
             save_period,
             task: args.task,
+            layout: args.layout,
+            parser: args.combo,
             parts_n,
         }
     }
 
-    fn process_line(_: &str) -> Option<String> {
+    fn process_line(&self, _: &str) -> Option<String> {
         unreachable!()
     }
 
-    fn process(mut self) -> Result<(), CoreError> {
+    fn process(self) -> Result<(), CoreError> {
         println!("Обработка {} файлов", self.targets.len());
 
         let now = time::Instant::now();
@@ -67,16 +73,6 @@ impl LinesProcessor for ByPartsSplitter {
                 lines_count
             );
 
-            let mut lines_n = lines_count / self.parts_n;
-
-            if lines_n == 0 {
-                lines_n = lines_count;
-            }
-
-            if self.save_period > lines_n {
-                self.save_period = lines_n;
-            }
-
             let file = match open_file_r(path) {
                 Ok(file) => file,
                 Err(err) => {
@@ -89,15 +85,13 @@ impl LinesProcessor for ByPartsSplitter {
 
             let mut part = 1;
 
-            // TODO: handle files with the same names but in a different dirs
             let suffix = self.task.to_suffix().replace("{num}", &part.to_string());
-            let mut self_results_path = self.results_path.clone();
-            self_results_path.push(path.file_name().unwrap_or_default());
+            let self_results_path = self.layout.file_dir(path, &self.results_path);
 
             let mut results_path = utils::build_results_path(path, &self_results_path, &suffix);
             let mut results_file = Some(utils::open_results_file(results_path)?);
 
-            let mut already_written = 0usize;
+            let mut cursor = PartCursor::new(lines_count, self.parts_n);
 
             for (i, combo) in reader.lines().enumerate() {
                 let combo = match combo {
@@ -113,46 +107,45 @@ impl LinesProcessor for ByPartsSplitter {
                     }
                 };
 
-                results.push(combo);
-
-                let next_group = i % lines_n == 1 && i > lines_n;
-
-                let last_combo = lines_count - i == 1;
-
-                // we have the second branch for saving results which we are not going to save right away
-                // so we need to save it only if don't need to switch to the next file
-                // in another way we will be just saving results from the second branch hence results files won't contains needed ammount of lines
-                // UPD: we also need to do lines_n - already_written >= self.save_period, because otherwise it write unnecessary data before next_group will be true
-                let need_save = results.len() == self.save_period
-                    && !next_group
-                    && lines_n - already_written >= self.save_period;
-
-                if need_save || last_combo {
-                    if let Err(e) = utils::save_results(&mut results, &results_file) {
+                let line_part = cursor.next_part();
+                while part < line_part {
+                    if let Err(e) =
+                        utils::save_results(&mut results, &results_file, Some(&self.parser))
+                    {
                         eprintln!("Couldn't write to file: {}", e);
                     }
-                    already_written += self.save_period;
-                } else if next_group && already_written != lines_n {
-                    let need_write = lines_n - already_written;
 
-                    let mut to_be_written: Vec<&String> = results.iter().take(need_write).collect();
+                    part += 1;
+                    let suffix = self.task.to_suffix().replace("{num}", &part.to_string());
+                    results_path = utils::build_results_path(path, &self_results_path, &suffix);
+                    results_file = Some(utils::open_results_file(results_path)?);
+                }
+
+                results.push(combo);
 
-                    if let Err(e) = utils::save_results(&mut to_be_written, &results_file) {
+                if results.len() == self.save_period {
+                    if let Err(e) =
+                        utils::save_results(&mut results, &results_file, Some(&self.parser))
+                    {
                         eprintln!("Couldn't write to file: {}", e);
                     }
-
-                    results.truncate(need_write);
                 }
+            }
 
-                if next_group {
-                    part += 1;
-                    let suffix = self.task.to_suffix().replace("{num}", &part.to_string());
-                    results_path = utils::build_results_path(path, &self_results_path, &suffix);
-                    results_file = Some(utils::open_results_file(results_path)?);
-                    already_written = 0;
+            if !results.is_empty() {
+                if let Err(e) = utils::save_results(&mut results, &results_file, Some(&self.parser))
+                {
+                    eprintln!("Couldn't write to file: {}", e);
                 }
             }
 
+            while part < self.parts_n {
+                part += 1;
+                let suffix = self.task.to_suffix().replace("{num}", &part.to_string());
+                results_path = utils::build_results_path(path, &self_results_path, &suffix);
+                utils::open_results_file(results_path)?;
+            }
+
             println!("Потрачено: {:?}", inner_now.elapsed());
         }
 
@@ -163,3 +156,141 @@ impl LinesProcessor for ByPartsSplitter {
         Ok(())
     }
 }
+
+/// Size of the 1-based `part`, the remainder is spread over the first parts one line each
+fn part_size(lines_count: usize, parts_n: usize, part: usize) -> usize {
+    lines_count / parts_n + usize::from(part <= lines_count % parts_n)
+}
+
+/// Tracks the part which the next line goes to
+struct PartCursor {
+    lines_count: usize,
+    parts_n: usize,
+    part: usize,
+    written: usize,
+}
+
+impl PartCursor {
+    fn new(lines_count: usize, parts_n: usize) -> Self {
+        PartCursor {
+            lines_count,
+            parts_n,
+            part: 1,
+            written: 0,
+        }
+    }
+
+    /// 1-based part of the next line, parts may be empty when there are fewer lines than parts
+    fn next_part(&mut self) -> usize {
+        while self.written == part_size(self.lines_count, self.parts_n, self.part)
+            && self.part < self.parts_n
+        {
+            self.part += 1;
+            self.written = 0;
+        }
+        self.written += 1;
+        self.part
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{ffi::OsString, fs};
+
+    use rand::{Rng, SeedableRng};
+    use rand_chacha::ChaCha8Rng;
+
+    use super::{ByPartsSplitter, PartCursor};
+    use crate::{
+        cmd,
+        core::{lines_processor::LinesProcessor, utils},
+    };
+
+    /// Splits lines `0..lines_count` into parts the way the task does
+    fn split(lines_count: usize, parts_n: usize) -> Vec<Vec<usize>> {
+        let mut parts = vec![Vec::new(); parts_n];
+        let mut cursor = PartCursor::new(lines_count, parts_n);
+        for line in 0..lines_count {
+            parts[cursor.next_part() - 1].push(line);
+        }
+        parts
+    }
+
+    fn check(lines_count: usize, parts_n: usize) {
+        let parts = split(lines_count, parts_n);
+        assert_eq!(parts.len(), parts_n);
+
+        let sizes: Vec<usize> = parts.iter().map(Vec::len).collect();
+        let (min, max) = (sizes.iter().min().unwrap(), sizes.iter().max().unwrap());
+        assert!(
+            max - min <= 1,
+            "{} lines, {} parts: {:?}",
+            lines_count,
+            parts_n,
+            sizes
+        );
+        assert_eq!(sizes.iter().sum::<usize>(), lines_count);
+
+        let joined: Vec<usize> = parts.into_iter().flatten().collect();
+        assert_eq!(joined, (0..lines_count).collect::<Vec<usize>>());
+    }
+
+    #[test]
+    fn small_sizes() {
+        for lines_count in 0..=200 {
+            for parts_n in 1..=50 {
+                check(lines_count, parts_n);
+            }
+        }
+    }
+
+    #[test]
+    fn splits_file_into_parts() {
+        let dir = utils::test_dir("split_by_parts");
+        let target = dir.join("combos.txt");
+        let lines: Vec<String> = (0..11)
+            .map(|i| format!("user{}@mail.com:pass{}", i, i))
+            .collect();
+        fs::write(&target, lines.join("\n") + "\n").unwrap();
+        let results_path = dir.join("results");
+
+        let args = [
+            "combosutils",
+            "--task",
+            "split-by-parts",
+            "-n",
+            "4",
+            "--target",
+        ]
+        .iter()
+        .map(OsString::from)
+        .chain([target.into_os_string()]);
+        // the save period is smaller than the parts, so they are written in several chunks
+        ByPartsSplitter::new(cmd::parse_args_from(args).unwrap(), results_path.clone(), 2)
+            .process()
+            .unwrap();
+
+        let results = utils::test_results(&results_path);
+        let parts: Vec<Vec<String>> = results
+            .iter()
+            .map(|part| utils::read_decoded_lines(part).unwrap())
+            .collect();
+        let sizes: Vec<usize> = parts.iter().map(Vec::len).collect();
+        assert_eq!(sizes, [3, 3, 3, 2]);
+
+        let joined: Vec<String> = parts.into_iter().flatten().collect();
+        assert_eq!(joined, lines);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn random_sizes() {
+        let mut rng = ChaCha8Rng::seed_from_u64(34);
+        for _ in 0..200 {
+            let lines_count = rng.gen_range(0..100_000);
+            let parts_n = rng.gen_range(1..1_000);
+            check(lines_count, parts_n);
+        }
+    }
+}
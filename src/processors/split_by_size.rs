@@ -0,0 +1,160 @@
+use std::{io::BufRead, path::PathBuf, time};
+
+use encoding_rs::WINDOWS_1252;
+
+use crate::{
+    cmd::Args,
+    core::{
+        combo::ComboParser,
+        lines_processor::LinesProcessor,
+        results_layout::ResultsLayout,
+        task::Task,
+        utils::{self, open_file_r},
+    },
+    errors::core_error::CoreError,
+};
+
+pub struct BySizeSplitter {
+    targets: Vec<PathBuf>,
+    results_path: PathBuf,
+    save_period: usize,
+    task: Task,
+    layout: ResultsLayout,
+    parser: ComboParser,
+    max_bytes: usize,
+}
+
+impl LinesProcessor for BySizeSplitter {
+    fn new(args: Args, results_path: PathBuf, save_period: usize) -> Self {
+        let max_bytes = if let Some(max_bytes) = args.max_bytes {
+            max_bytes
+        } else {
+            utils::ask_for_number("Максимальный размер файла в байтах: ")
+        };
+
+        BySizeSplitter {
+            targets: args.targets,
+            results_path,
+            save_period,
+            task: args.task,
+            layout: args.layout,
+            parser: args.combo,
+            max_bytes,
+        }
+    }
+
+    fn process_line(&self, _: &str) -> Option<String> {
+        unreachable!()
+    }
+
+    fn process(self) -> Result<(), CoreError> {
+        println!("Обработка {} файлов", self.targets.len());
+
+        let now = time::Instant::now();
+
+        let mut results: Vec<String> = Vec::with_capacity(self.save_period);
+
+        for (file_num, path) in self.targets.iter().enumerate() {
+            let inner_now = time::Instant::now();
+
+            let file = match open_file_r(path) {
+                Ok(file) => file,
+                Err(err) => {
+                    eprintln!("Can't read input file {}. {}", path.display(), err);
+                    continue;
+                }
+            };
+
+            println!(
+                "[{}/{}]Файл: {}",
+                file_num + 1,
+                self.targets.len(),
+                path.display()
+            );
+
+            let reader = utils::reader_from_file(file);
+
+            let mut part = 1;
+
+            let suffix = self.task.to_suffix().replace("{num}", &part.to_string());
+            let self_results_path = self.layout.file_dir(path, &self.results_path);
+
+            let mut results_path = utils::build_results_path(path, &self_results_path, &suffix);
+            let mut results_file = Some(utils::open_results_file(results_path)?);
+
+            let mut part_bytes = 0usize;
+
+            for (i, combo) in reader.lines().enumerate() {
+                let combo = match combo {
+                    Ok(combo) => combo,
+                    Err(err) => {
+                        eprintln!(
+                            "Can't read combo on line {} in file {}. {}",
+                            i,
+                            path.display(),
+                            err
+                        );
+                        continue;
+                    }
+                };
+
+                // parts are measured by the lines as they are written
+                let combo = self.parser.format_string(combo);
+                let combo_bytes = encoded_len(&combo) + 1;
+
+                if combo_bytes > self.max_bytes {
+                    eprintln!(
+                        "Line {} in file {} is longer than {} bytes, it's written to a separate part",
+                        i,
+                        path.display(),
+                        self.max_bytes
+                    );
+                }
+
+                if part_bytes > 0 && part_bytes + combo_bytes > self.max_bytes {
+                    if let Err(e) = utils::save_results(&mut results, &results_file, None) {
+                        eprintln!("Couldn't write to file: {}", e);
+                    }
+
+                    part += 1;
+                    let suffix = self.task.to_suffix().replace("{num}", &part.to_string());
+                    results_path = utils::build_results_path(path, &self_results_path, &suffix);
+                    results_file = Some(utils::open_results_file(results_path)?);
+                    part_bytes = 0;
+                }
+
+                results.push(combo);
+                part_bytes += combo_bytes;
+
+                if results.len() == self.save_period {
+                    if let Err(e) = utils::save_results(&mut results, &results_file, None) {
+                        eprintln!("Couldn't write to file: {}", e);
+                    }
+                }
+            }
+
+            if !results.is_empty() {
+                if let Err(e) = utils::save_results(&mut results, &results_file, None) {
+                    eprintln!("Couldn't write to file: {}", e);
+                }
+            }
+
+            println!("Частей: {}. Потрачено: {:?}", part, inner_now.elapsed());
+        }
+
+        if self.targets.len() > 1 {
+            println!("Потрачено в общем: {:?}", now.elapsed());
+        }
+
+        Ok(())
+    }
+}
+
+/// Length of the line as it's written to the results file
+fn encoded_len(combo: &str) -> usize {
+    if combo.is_ascii() {
+        combo.len()
+    } else {
+        WINDOWS_1252.encode(combo).0.len()
+    }
+}